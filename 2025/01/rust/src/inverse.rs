@@ -0,0 +1,90 @@
+use crate::{Dial, Rule, solve_reader};
+use anyhow::{Context, Result};
+
+// Builds rotations that produce exactly `part1` landings on zero and `part2`
+// passes through zero when applied to a dial of `positions` starting at
+// `start`. Uses as few lines as possible: every landing needs its own line,
+// while any number of extra passes fit into a single rotation.
+pub fn synthesize(
+    positions: i64,
+    start: i64,
+    part1: i64,
+    part2: i64,
+    max_lines: usize,
+) -> Result<Vec<String>> {
+    Dial::new(positions, start, Rule::PassThroughZero)?;
+    if part1 < 0 || part2 < 0 {
+        anyhow::bail!("click counts must not be negative");
+    }
+    // Landing on zero is also a pass through zero. The only way around that
+    // is a zero-length rotation, which real inputs never contain.
+    if part1 > part2 {
+        anyhow::bail!(
+            "part1 clicks ({}) can't exceed part2 clicks ({})",
+            part1,
+            part2
+        );
+    }
+    let extra = part2 - part1;
+    // The rotation carrying the extra passes must fit in a single line.
+    let too_long = || {
+        format!(
+            "{} extra passes on a dial of {} need a rotation longer than {} steps",
+            extra,
+            positions,
+            i64::MAX
+        )
+    };
+    // every landing needs its own line, extra passes alone need one
+    let needed = if part1 > 0 { part1 } else { (extra > 0) as i64 };
+    if needed as u64 > max_lines as u64 {
+        anyhow::bail!(
+            "target needs at least {} lines, max is {}",
+            needed,
+            max_lines
+        );
+    }
+
+    let mut lines = Vec::new();
+    if part1 > 0 {
+        // go to zero, passing it `extra` times on the way, then full turns
+        let steps = extra
+            .checked_mul(positions)
+            .and_then(|turns| turns.checked_add(positions - start))
+            .with_context(too_long)?;
+        lines.push(format!("R{}", steps));
+        for _ in 1..part1 {
+            lines.push(format!("R{}", positions));
+        }
+    } else if extra > 0 {
+        // full turns from a non-zero position, leaving zero first if needed
+        if positions == 1 {
+            anyhow::bail!("a dial with a single position lands on zero on every rotation");
+        }
+        let leave = if start == 0 { 1 } else { 0 };
+        let steps = extra
+            .checked_mul(positions)
+            .and_then(|turns| turns.checked_add(leave))
+            .with_context(too_long)?;
+        lines.push(format!("R{}", steps));
+    }
+    verify(&lines, positions, start, part1, part2)?;
+    Ok(lines)
+}
+
+fn verify(lines: &[String], positions: i64, start: i64, part1: i64, part2: i64) -> Result<()> {
+    let content = lines.join("\n");
+    for (rule, expected) in [(Rule::LandOnZero, part1), (Rule::PassThroughZero, part2)] {
+        let dial = Dial::new(positions, start, rule)?;
+        let actual = solve_reader(content.as_bytes(), &dial, None, |_| {})?;
+        if actual != expected {
+            anyhow::bail!(
+                "{:?}: synthesized {} clicks, expected {}",
+                dial.rule,
+                actual,
+                expected
+            );
+        }
+    }
+    Ok(())
+}
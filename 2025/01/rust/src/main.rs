@@ -1,105 +1,601 @@
 use anyhow::{Context, Result, anyhow};
 use std::env;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader};
 
-fn solve_file<F>(path: &str, advance: F) -> Result<i32>
+mod inverse;
+mod trace;
+use trace::TraceFormat;
+
+fn solve_file<F>(path: &str, dial: &Dial, every: Option<u64>, checkpoint: F) -> Result<i64>
 where
-    F: Fn(&i32, &i32) -> (i32, i32),
+    F: FnMut(Checkpoint),
 {
-    let mut content = String::new();
-    File::open(path)?.read_to_string(&mut content)?;
-    solve_str(&content, advance)
+    let reader = BufReader::new(File::open(path)?);
+    solve_reader(reader, dial, every, checkpoint)
+}
+
+#[cfg(test)]
+fn solve_str(content: &str, dial: &Dial) -> Result<i64> {
+    solve_reader(content.as_bytes(), dial, None, |_| {})
 }
 
-fn solve_str<F>(content: &str, advance: F) -> Result<i32>
+#[derive(Debug, Clone, PartialEq)]
+struct Checkpoint {
+    line: u64,
+    position: i64,
+    clicks: i64,
+}
+
+// Consumes rotations one line at a time, so memory does not depend on the
+// size of the input. `checkpoint` is called after every `every` lines.
+fn solve_reader<R, F>(
+    mut reader: R,
+    dial: &Dial,
+    every: Option<u64>,
+    mut checkpoint: F,
+) -> Result<i64>
 where
-    F: Fn(&i32, &i32) -> (i32, i32),
+    R: BufRead,
+    F: FnMut(Checkpoint),
 {
-    let distances: Vec<i32> = content
-        .lines()
-        .map(|line| {
-            let direction: i32 = match line.chars().nth(0).ok_or(anyhow!("empty string"))? {
-                'L' => -1,
-                'R' => 1,
-                _ => anyhow::bail!("unexpected input {}", line),
-            };
-            let steps: i32 = line
-                .chars()
-                .skip(1)
-                .collect::<String>()
-                .parse()
-                .context("invalid distance")?;
-            Ok(direction * steps)
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-    let clicks = distances
-        .iter()
-        .scan(50, |pos, distance| {
-            let (next, clicks) = advance(pos, distance);
-            *pos = next;
-            Some(clicks)
-        })
-        .sum();
+    let mut buf = String::new();
+    let mut line: u64 = 0;
+    let mut position = dial.start;
+    let mut clicks: i64 = 0;
+    loop {
+        buf.clear();
+        if reader.read_line(&mut buf)? == 0 {
+            break;
+        }
+        line += 1;
+        let offt = parse_rotation(buf.trim_end_matches(['\n', '\r']))
+            .with_context(|| format!("line {}", line))?;
+        let (next, n) = dial
+            .advance(&position, &offt)
+            .with_context(|| format!("line {}", line))?;
+        position = next;
+        clicks = clicks
+            .checked_add(n)
+            .with_context(|| format!("line {}: too many clicks", line))?;
+        if every.is_some_and(|every| line.is_multiple_of(every)) {
+            checkpoint(Checkpoint {
+                line,
+                position,
+                clicks,
+            });
+        }
+    }
     Ok(clicks)
 }
 
-fn part1(pos: &i32, offt: &i32) -> (i32, i32) {
-    let next = (100 + (pos + offt) % 100).abs() % 100;
-    // println!(
-    //     "pos={} offt={} next={} click={}",
-    //     pos,
-    //     offt,
-    //     next,
-    //     next == 0
-    // );
+fn parse_rotation(line: &str) -> Result<i64> {
+    let direction: i64 = match line.chars().next().ok_or(anyhow!("empty string"))? {
+        'L' => -1,
+        'R' => 1,
+        _ => anyhow::bail!("unexpected input {}", line),
+    };
+    let distance = &line[1..];
+    // the direction is the sign, and u64 parsing would accept a '+'
+    if distance.starts_with(['+', '-']) {
+        anyhow::bail!("invalid distance {:?}: must not be signed", distance);
+    }
+    let steps: u64 = distance.parse().context("invalid distance")?;
+    let steps: i64 = steps
+        .try_into()
+        .with_context(|| format!("distance {} is too large", steps))?;
+    Ok(direction * steps)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Rule {
+    // count rotations that stop at 0
+    LandOnZero,
+    // count every time 0 is reached, including mid-rotation
+    PassThroughZero,
+    // count every time any of the marked positions is reached
+    PassThroughMarked(Vec<i64>),
+}
+
+impl TryFrom<&str> for Rule {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "land-on-zero" => Ok(Rule::LandOnZero),
+            "pass-through-zero" => Ok(Rule::PassThroughZero),
+            _ => {
+                let marks = s
+                    .strip_prefix("pass-through=")
+                    .ok_or(anyhow!("unexpected rule: {}", s))?;
+                let mut marks = marks
+                    .split(",")
+                    .map(|m| m.parse())
+                    .collect::<Result<Vec<i64>, _>>()
+                    .with_context(|| format!("invalid marks: {:?}", marks))?;
+                marks.sort();
+                marks.dedup();
+                Ok(Rule::PassThroughMarked(marks))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Dial {
+    positions: i64,
+    start: i64,
+    rule: Rule,
+}
+
+impl Dial {
+    fn new(positions: i64, start: i64, rule: Rule) -> Result<Self> {
+        if positions <= 0 {
+            anyhow::bail!(
+                "dial must have positive number of positions, got {}",
+                positions
+            );
+        }
+        if !(0..positions).contains(&start) {
+            anyhow::bail!("start {} is outside of the dial [0,{})", start, positions);
+        }
+        if let Rule::PassThroughMarked(marks) = &rule
+            && let Some(m) = marks.iter().find(|m| !(0..positions).contains(*m))
+        {
+            anyhow::bail!("mark {} is outside of the dial [0,{})", m, positions);
+        }
+        Ok(Self {
+            positions,
+            start,
+            rule,
+        })
+    }
+
+    fn with_rule(rule: Rule) -> Self {
+        Self {
+            positions: 100,
+            start: 50,
+            rule,
+        }
+    }
+
+    fn advance(&self, pos: &i64, offt: &i64) -> Result<(i64, i64)> {
+        match &self.rule {
+            Rule::LandOnZero => Ok(part1(self.positions, pos, offt)),
+            Rule::PassThroughZero => part2(self.positions, pos, offt),
+            Rule::PassThroughMarked(marks) => {
+                // passing a mark is passing zero on a dial rotated by the mark
+                let clicks = marks.iter().try_fold(0i64, |acc, m| {
+                    let (_, clicks) =
+                        part2(self.positions, &(pos - m).rem_euclid(self.positions), offt)?;
+                    acc.checked_add(clicks).context("too many clicks")
+                })?;
+                Ok((rotate(self.positions, pos, offt), clicks))
+            }
+        }
+    }
+}
+
+// Positions are widened to i128 so rotations close to i64::MAX don't overflow;
+// any rotation parsed from input (|offt| <= i64::MAX) has its result and
+// number of clicks fit back into i64.
+fn rotate(size: i64, pos: &i64, offt: &i64) -> i64 {
+    (*pos as i128 + *offt as i128).rem_euclid(size as i128) as i64
+}
+
+fn part1(size: i64, pos: &i64, offt: &i64) -> (i64, i64) {
+    let next = rotate(size, pos, offt);
     match next == 0 {
         true => (next, 1),
         false => (next, 0),
     }
 }
 
-fn part2(pos: &i32, offt: &i32) -> (i32, i32) {
-    let diff = pos + offt;
+fn part2(size: i64, pos: &i64, offt: &i64) -> Result<(i64, i64)> {
+    let diff = *pos as i128 + *offt as i128;
 
-    let mut clicks = diff.abs() / 100;
+    let mut clicks = diff.abs() / size as i128;
     if diff <= 0 && *pos != 0 {
         clicks += 1;
     }
-    let next = (100 + diff % 100).abs() % 100;
-    // println!("pos={} offt={} next={} click={}", pos, offt, next, clicks);
-    (next, clicks)
+    let next = rotate(size, pos, offt);
+    let clicks = clicks.try_into().context("too many clicks")?;
+    Ok((next, clicks))
+}
+
+struct Options {
+    dial: Dial,
+    trace: Option<TraceFormat>,
+    checkpoint: Option<u64>,
+}
+
+fn parse_options(rule: Rule, flags: &[String]) -> Result<Options> {
+    let mut dial = Dial::with_rule(rule);
+    let mut trace = None;
+    let mut checkpoint = None;
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let value = flags
+            .next()
+            .with_context(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--positions" => dial.positions = value.parse().context("invalid --positions")?,
+            "--start" => dial.start = value.parse().context("invalid --start")?,
+            "--rule" => dial.rule = value.as_str().try_into()?,
+            "--trace" => trace = Some(value.as_str().try_into()?),
+            "--checkpoint" => match value.parse().context("invalid --checkpoint")? {
+                0 => anyhow::bail!("--checkpoint must be positive"),
+                n => checkpoint = Some(n),
+            },
+            s => anyhow::bail!("unexpected flag: {}", s),
+        }
+    }
+    Ok(Options {
+        dial: Dial::new(dial.positions, dial.start, dial.rule)?,
+        trace,
+        checkpoint,
+    })
+}
+
+// synthesize --positions N --start S --part1 A --part2 B --max-lines L
+fn synthesize(flags: &[String]) -> Result<Vec<String>> {
+    let (mut positions, mut start) = (100, 50);
+    let (mut part1, mut part2, mut max_lines) = (None, None, usize::MAX);
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let value = flags
+            .next()
+            .with_context(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--positions" => positions = value.parse().context("invalid --positions")?,
+            "--start" => start = value.parse().context("invalid --start")?,
+            "--part1" => part1 = Some(value.parse().context("invalid --part1")?),
+            "--part2" => part2 = Some(value.parse().context("invalid --part2")?),
+            "--max-lines" => max_lines = value.parse().context("invalid --max-lines")?,
+            s => anyhow::bail!("unexpected flag: {}", s),
+        }
+    }
+    inverse::synthesize(
+        positions,
+        start,
+        part1.context("missing --part1")?,
+        part2.context("missing --part2")?,
+        max_lines,
+    )
 }
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
-    let path = args.get(1).context("missing file path argument")?;
-    match args.get(2).map(|s| s.as_str()).context("missing part")? {
-        "part1" => {
-            println!("solution: {:#?}", solve_file(path, part1)?)
-        }
-        "part2" => {
-            println!("solution: {:#?}", solve_file(path, part2)?)
+    if args.get(1).is_some_and(|s| s == "synthesize") {
+        for line in synthesize(&args[2..])? {
+            println!("{}", line);
         }
+        return Ok(());
+    }
+    let path = args.get(1).context("missing file path argument")?;
+    let rule = match args.get(2).map(|s| s.as_str()).context("missing part")? {
+        "part1" => Rule::LandOnZero,
+        "part2" => Rule::PassThroughZero,
         s => anyhow::bail!("unexpected part: {}", s),
     };
+    let options = parse_options(rule, &args[3..])?;
+    match options.trace {
+        Some(format) => {
+            let content = std::fs::read_to_string(path)?;
+            let steps = trace::trace_str(&content, &options.dial)?;
+            print!("{}", format.render(&steps)?);
+        }
+        None => {
+            let clicks = solve_file(path, &options.dial, options.checkpoint, |c| {
+                println!(
+                    "checkpoint: line={} position={} clicks={}",
+                    c.line, c.position, c.clicks
+                )
+            })?;
+            println!("solution: {:#?}", clicks)
+        }
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use difftest::{Harness, Rng};
     const INPUT: &str = "L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82";
 
     #[test]
     fn test_part_1() {
-        let actual = solve_str(INPUT, part1).expect("must solve");
+        let actual = solve_str(INPUT, &Dial::with_rule(Rule::LandOnZero)).expect("must solve");
         assert_eq!(3, actual);
     }
 
     #[test]
     fn test_part_2() {
-        let actual = solve_str(INPUT, part2).expect("must solve");
+        let actual = solve_str(INPUT, &Dial::with_rule(Rule::PassThroughZero)).expect("must solve");
         assert_eq!(6, actual);
     }
+
+    #[test]
+    fn test_dial() {
+        let flags = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        // marking only 0 is the same as part2
+        let dial = parse_options(Rule::LandOnZero, &flags("--rule pass-through=0"))
+            .unwrap()
+            .dial;
+        assert_eq!(6, solve_str(INPUT, &dial).expect("must solve"));
+        // 10 positions starting at 0: R3 L4 passes 0 once and lands on 9
+        let dial = parse_options(Rule::PassThroughZero, &flags("--positions 10 --start 0"))
+            .unwrap()
+            .dial;
+        assert_eq!(1, solve_str("R3\nL4", &dial).expect("must solve"));
+        let dial = parse_options(
+            Rule::LandOnZero,
+            &flags("--rule pass-through=3,9,3 --positions 10 --start 0"),
+        );
+        assert_eq!(Rule::PassThroughMarked(vec![3, 9]), dial.unwrap().dial.rule);
+        // R8 passes 9 and 3, L6 passes 9, R7 passes 9 and 3
+        let dial = Dial::new(10, 5, Rule::PassThroughMarked(vec![3, 9])).unwrap();
+        assert_eq!(5, solve_str("R8\nL6\nR7", &dial).expect("must solve"));
+        assert!(parse_options(Rule::LandOnZero, &flags("--positions 10 --start 10")).is_err());
+        assert!(
+            parse_options(
+                Rule::LandOnZero,
+                &flags("--rule pass-through=10 --positions 10")
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let dial = Dial::with_rule(Rule::PassThroughZero);
+        let steps = trace::trace_str(INPUT, &dial).expect("must trace");
+        assert_eq!(10, steps.len());
+        assert_eq!(6, steps.iter().map(|s| s.clicks).sum::<i64>());
+        // L55 from 55 stops exactly on 0, then L1 leaves 0 without a click
+        assert_eq!(
+            (6, 55, 0, 1),
+            (steps[5].line, steps[5].start, steps[5].end, steps[5].clicks)
+        );
+        assert_eq!(
+            (7, 0, 99, 0),
+            (steps[6].line, steps[6].start, steps[6].end, steps[6].clicks)
+        );
+
+        let csv = TraceFormat::Csv.render(&steps[..2]).unwrap();
+        assert_eq!(
+            "line,direction,distance,start,end,clicks\n1,L,68,50,82,1\n2,L,30,82,52,0\n",
+            csv
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&TraceFormat::Json.render(&steps).unwrap()).unwrap();
+        assert_eq!(json[9]["direction"], "L");
+        assert_eq!(json[9]["end"], 32);
+
+        let err = trace::trace_str("R1\nX2", &dial).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("line 2"));
+    }
+
+    // Reference for Dial::advance: turn the dial one position at a time.
+    fn simulate(dial: &Dial, pos: &i64, offt: &i64) -> (i64, i64) {
+        // Clicks for reaching each position, looked up per step: rotations
+        // near i32::MAX take ~2^31 steps, so the loop has to stay cheap.
+        let hits: Vec<i64> = (0..dial.positions)
+            .map(|p| match &dial.rule {
+                Rule::LandOnZero => 0,
+                Rule::PassThroughZero => (p == 0) as i64,
+                Rule::PassThroughMarked(marks) => marks.contains(&p) as i64,
+            })
+            .collect();
+        let mut pos = *pos;
+        let mut clicks = 0;
+        for _ in 0..offt.unsigned_abs() {
+            pos += offt.signum();
+            if pos == dial.positions {
+                pos = 0;
+            } else if pos < 0 {
+                pos = dial.positions - 1;
+            }
+            clicks += hits[pos as usize];
+        }
+        if dial.rule == Rule::LandOnZero && pos == 0 {
+            clicks = 1;
+        }
+        (pos, clicks)
+    }
+
+    type Input = ((i64, i64, i64), Vec<i64>);
+
+    // Shrinking can produce any values, so clamp them into a valid dial.
+    fn dial_of(((size, pos, offt), marks): &Input, rule: &Rule) -> (Dial, i64) {
+        let size = (*size).max(1);
+        let rule = match rule {
+            Rule::PassThroughMarked(_) => {
+                let mut marks: Vec<i64> = marks.iter().map(|m| m.rem_euclid(size)).collect();
+                marks.sort();
+                marks.dedup();
+                Rule::PassThroughMarked(marks)
+            }
+            rule => rule.clone(),
+        };
+        let dial = Dial::new(size, pos.rem_euclid(size), rule).expect("valid dial");
+        (dial, *offt)
+    }
+
+    fn generate(rng: &mut Rng, max_offt: i64) -> Input {
+        let size = rng.range_i64(1, 120);
+        let pos = rng.range_i64(0, size - 1);
+        let offt = rng.range_i64(-max_offt, max_offt);
+        let marks = (0..rng.below(4))
+            .map(|_| rng.range_i64(0, size - 1))
+            .collect();
+        ((size, pos, offt), marks)
+    }
+
+    const RULES: [Rule; 3] = [
+        Rule::LandOnZero,
+        Rule::PassThroughZero,
+        Rule::PassThroughMarked(vec![]),
+    ];
+
+    #[test]
+    fn test_advance_matches_simulation() {
+        for rule in RULES {
+            Harness::new().check(
+                &format!("{:?}", rule),
+                |rng| generate(rng, 2_000),
+                |input| {
+                    let (dial, offt) = dial_of(input, &rule);
+                    simulate(&dial, &dial.start, &offt)
+                },
+                |input| {
+                    let (dial, offt) = dial_of(input, &rule);
+                    dial.advance(&dial.start, &offt).expect("must advance")
+                },
+            );
+        }
+    }
+
+    fn generate_huge(rng: &mut Rng, max: i64) -> Input {
+        let ((size, pos, _), marks) = generate(rng, 0);
+        let offt = max - rng.range_i64(0, 1_000_000);
+        ((size, pos, if rng.bool() { offt } else { -offt }), marks)
+    }
+
+    #[test]
+    fn test_huge_rotations() {
+        // Rotations near i64::MAX cannot be simulated, so check that they
+        // equal the same rotation done in two halves.
+        for rule in RULES {
+            Harness::new().check(
+                &format!("huge {:?} near {}", rule, i64::MAX),
+                |rng| generate_huge(rng, i64::MAX),
+                |input| {
+                    let (dial, offt) = dial_of(input, &rule);
+                    let (pos, clicks) = dial.advance(&dial.start, &(offt / 2)).unwrap();
+                    let (next, more) = dial.advance(&pos, &(offt - offt / 2)).unwrap();
+                    match rule {
+                        Rule::LandOnZero => (next, (next == 0) as i64),
+                        _ => (next, clicks + more),
+                    }
+                },
+                |input| {
+                    let (dial, offt) = dial_of(input, &rule);
+                    dial.advance(&dial.start, &offt).expect("must advance")
+                },
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "slow: simulates billions of steps, run with --release"]
+    fn test_huge_rotations_simulated() {
+        // About 2^31 steps per case, so only one case per rule.
+        for rule in RULES {
+            Harness::new().cases(1).check(
+                &format!("simulated huge {:?}", rule),
+                |rng| generate_huge(rng, i32::MAX as i64),
+                |input| {
+                    let (dial, offt) = dial_of(input, &rule);
+                    simulate(&dial, &dial.start, &offt)
+                },
+                |input| {
+                    let (dial, offt) = dial_of(input, &rule);
+                    dial.advance(&dial.start, &offt).expect("must advance")
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn test_solve_reader_checkpoints() {
+        let dial = Dial::with_rule(Rule::PassThroughZero);
+        let mut checkpoints = Vec::new();
+        let input = INPUT.replace("\n", "\r\n");
+        let actual = solve_reader(input.as_bytes(), &dial, Some(4), |c| checkpoints.push(c));
+        assert_eq!(6, actual.expect("must solve"));
+        let expected = [(4, 95, 2), (8, 0, 5)].map(|(line, position, clicks)| Checkpoint {
+            line,
+            position,
+            clicks,
+        });
+        assert_eq!(expected.to_vec(), checkpoints);
+
+        // positions and distances beyond i32
+        let dial = Dial::new(10_000_000_000, 0, Rule::PassThroughZero).unwrap();
+        let input = "R9999999999\nR1\nL30000000000\n";
+        assert_eq!(4, solve_str(input, &dial).expect("must solve"));
+        let err = solve_str("R1\n\nR2", &dial).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("line 2"));
+    }
+
+    #[test]
+    fn test_signed_distances() {
+        for line in [
+            "L-9223372036854775808",
+            "R-9223372036854775808",
+            "R+5",
+            "L-1",
+        ] {
+            let err = parse_rotation(line).unwrap_err();
+            assert!(err.to_string().contains("must not be signed"), "{}", line);
+        }
+        let err = parse_rotation("R9223372036854775808").unwrap_err();
+        assert_eq!("distance 9223372036854775808 is too large", err.to_string());
+
+        // the longest rotations still fit on the smallest dial
+        let dial = Dial::new(1, 0, Rule::PassThroughZero).unwrap();
+        let input = "R9223372036854775807\nL9223372036854775807\n";
+        let err = solve_str(input, &dial).unwrap_err();
+        assert_eq!("line 2: too many clicks", format!("{:#}", err));
+        let input = "L9223372036854775807\n";
+        assert_eq!(i64::MAX, solve_str(input, &dial).unwrap());
+    }
+
+    #[test]
+    fn test_synthesize() {
+        let mut rng = Rng::new(31);
+        for _ in 0..500 {
+            let positions = rng.range_i64(1, 200);
+            let start = rng.range_i64(0, positions - 1);
+            let part1 = rng.range_i64(0, 20);
+            let part2 = part1 + rng.range_i64(0, 1000);
+            if positions == 1 && part1 == 0 && part2 > 0 {
+                continue;
+            }
+            let lines =
+                inverse::synthesize(positions, start, part1, part2, 20).expect("must synthesize");
+            let content = lines.join("\n");
+            let dial = Dial::new(positions, start, Rule::LandOnZero).unwrap();
+            assert_eq!(part1, solve_str(&content, &dial).unwrap());
+            let dial = Dial::new(positions, start, Rule::PassThroughZero).unwrap();
+            assert_eq!(part2, solve_str(&content, &dial).unwrap());
+        }
+
+        assert_eq!(
+            vec!["R550", "R100", "R100"],
+            inverse::synthesize(100, 50, 3, 8, 3).unwrap()
+        );
+        assert!(inverse::synthesize(100, 50, 3, 8, 2).is_err());
+        let err = inverse::synthesize(100, 50, 100_000_000_000, 100_000_000_000, 5).unwrap_err();
+        assert_eq!(
+            "target needs at least 100000000000 lines, max is 5",
+            err.to_string()
+        );
+        let err = inverse::synthesize(100, 50, 0, 1, 0).unwrap_err();
+        assert_eq!("target needs at least 1 lines, max is 0", err.to_string());
+        assert!(inverse::synthesize(100, 50, 3, 2, 10).is_err());
+        assert!(inverse::synthesize(1, 0, 0, 2, 10).is_err());
+        assert_eq!(0, inverse::synthesize(100, 0, 0, 0, 0).unwrap().len());
+
+        // rotations that would not fit in an i64
+        let huge = 100_000_000_000_000_000;
+        let err = inverse::synthesize(100, 50, 1, huge, 10).unwrap_err();
+        assert!(err.to_string().contains("need a rotation longer than"));
+        assert!(inverse::synthesize(100, 0, 0, huge, 10).is_err());
+        let most = i64::MAX / 100 - 1;
+        let lines = inverse::synthesize(100, 50, 1, most + 1, 1).expect("must synthesize");
+        assert_eq!(vec![format!("R{}", most * 100 + 50)], lines);
+    }
 }
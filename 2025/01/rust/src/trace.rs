@@ -0,0 +1,75 @@
+use crate::{Dial, parse_rotation};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+// One rotation line as it was applied to the dial.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Step {
+    pub line: usize,
+    pub direction: char,
+    pub distance: i64,
+    pub start: i64,
+    pub end: i64,
+    pub clicks: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    Csv,
+    Json,
+}
+
+impl TryFrom<&str> for TraceFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "csv" => Ok(TraceFormat::Csv),
+            "json" => Ok(TraceFormat::Json),
+            _ => anyhow::bail!("unexpected trace format: {}", s),
+        }
+    }
+}
+
+impl TraceFormat {
+    pub fn render(&self, steps: &[Step]) -> Result<String> {
+        match self {
+            TraceFormat::Csv => {
+                let mut out = String::from("line,direction,distance,start,end,clicks\n");
+                for s in steps {
+                    out += &format!(
+                        "{},{},{},{},{},{}\n",
+                        s.line, s.direction, s.distance, s.start, s.end, s.clicks
+                    );
+                }
+                Ok(out)
+            }
+            TraceFormat::Json => {
+                Ok(serde_json::to_string_pretty(steps).context("must serialize trace")? + "\n")
+            }
+        }
+    }
+}
+
+pub fn trace_str(content: &str, dial: &Dial) -> Result<Vec<Step>> {
+    let mut pos = dial.start;
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let (offt, (next, clicks)) = parse_rotation(line)
+                .and_then(|offt| Ok((offt, dial.advance(&pos, &offt)?)))
+                .with_context(|| format!("line {}", i + 1))?;
+            let step = Step {
+                line: i + 1,
+                direction: line.chars().next().expect("validated by parse_rotation"),
+                distance: offt.abs(),
+                start: pos,
+                end: next,
+                clicks,
+            };
+            pos = next;
+            Ok(step)
+        })
+        .collect()
+}
@@ -0,0 +1,66 @@
+use crate::rules::Rules;
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+// Invalid IDs found in one input range; `ids` holds at most the requested
+// number of IDs, `more` counts the rest.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explained {
+    pub start: u128,
+    pub end: u128,
+    pub count: u128,
+    pub sum: u128,
+    pub ids: Vec<u128>,
+    pub more: u128,
+}
+
+pub fn explain(ranges: &[(u128, u128)], rules: &Rules, limit: usize) -> Result<Vec<Explained>> {
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            let (count, sum) = rules
+                .sum(start, end)
+                .with_context(|| format!("sum of invalid ids in {}-{} overflows", start, end))?;
+            let ids: Vec<u128> = rules.ids(start, end).take(limit).collect();
+            Ok(Explained {
+                start,
+                end,
+                count,
+                sum,
+                more: count - ids.len() as u128,
+                ids,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn render(&self, ranges: &[Explained]) -> Result<String> {
+        match self {
+            Format::Text => {
+                let mut out = String::new();
+                for r in ranges {
+                    out += &format!("{}-{}: {} invalid, sum {}", r.start, r.end, r.count, r.sum);
+                    if !r.ids.is_empty() {
+                        let ids: Vec<String> = r.ids.iter().map(|id| id.to_string()).collect();
+                        out += &format!(": {}", ids.join(", "));
+                    }
+                    if r.more > 0 {
+                        out += &format!(" …and {} more", r.more);
+                    }
+                    out += "\n";
+                }
+                Ok(out)
+            }
+            Format::Json => Ok(serde_json::to_string_pretty(ranges)
+                .context("must serialize explanation")?
+                + "\n"),
+        }
+    }
+}
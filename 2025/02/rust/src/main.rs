@@ -2,26 +2,46 @@ use anyhow::{Context, Result, anyhow};
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::thread;
 
-fn solve_file<F>(path: &str, f: F) -> Result<i64>
-where
-    F: Fn(i64) -> bool + Clone,
-{
+mod explain;
+mod parallel;
+mod repeated;
+mod rules;
+use rules::{Rule, Rules};
+
+fn solve_file(path: &str, options: &Options) -> Result<u128> {
     let mut content = String::new();
     File::open(path)?.read_to_string(&mut content)?;
-    solve_str(&content, f)
+    let rules = &options.rules;
+    match options.threads {
+        1 => solve_str(&content, options.overlap, |start, end| {
+            sum_invalid(rules, start, end)
+        }),
+        n => parallel::sum_ranges(&parse_ranges(&content, options.overlap)?, rules, n),
+    }
+}
+
+fn sum_invalid(rules: &Rules, start: u128, end: u128) -> Result<u128> {
+    let (_, sum) = rules
+        .sum(start, end)
+        .with_context(|| format!("sum of invalid ids in {}-{} overflows", start, end))?;
+    Ok(sum)
 }
 
-fn part1(id: i64) -> bool {
+// Original per-ID predicates, kept as the oracle for the enumeration.
+#[cfg(test)]
+fn valid_part1(id: i64) -> bool {
     let s = id.to_string();
-    if s.len() % 2 != 0 {
+    if !s.len().is_multiple_of(2) {
         return true;
     }
     let (first, second) = s.split_at(s.len() / 2);
     first != second
 }
 
-fn part2(id: i64) -> bool {
+#[cfg(test)]
+fn valid_part2(id: i64) -> bool {
     let b = id.to_string().into_bytes();
     for i in 1..b.len() {
         if b[i..].starts_with(&b[0..i]) {
@@ -36,76 +56,453 @@ fn part2(id: i64) -> bool {
             }
         }
     }
-    return true;
+    true
 }
 
-fn sum_invalid_ids<F>(range: &str, is_valid_id: F) -> Result<i64>
+#[cfg(test)]
+fn sum_invalid_ids<F>(start: i64, end: i64, is_valid_id: F) -> i64
 where
     F: Fn(i64) -> bool,
 {
-    let parts: Vec<i64> = range
+    let mut sum: i64 = 0;
+    for i in start..end + 1 {
+        if !is_valid_id(i) {
+            sum += i
+        }
+    }
+    sum
+}
+
+fn parse_range(range: &str) -> Result<(u128, u128)> {
+    let parts: Vec<u128> = range
         .split("-")
         .map(|s| s.parse())
         .collect::<Result<Vec<_>, _>>()
         .map_err(|_| anyhow!("must have valid range definition, got {:?}", range))?;
 
-    let [start, end]: [i64; 2] = parts
+    let [start, end]: [u128; 2] = parts
         .try_into()
         .map_err(|input| anyhow!("must have 2 numbers, got {:?}", input))?;
+    if start > end {
+        anyhow::bail!("reversed range {:?}: start must not exceed end", range);
+    }
+    Ok((start, end))
+}
 
-    let mut sum: i64 = 0;
-    for i in start..end + 1 {
-        if !is_valid_id(i) {
-            // println!("range [{},{}]: {} is invalid id", start, end, i);
-            sum += i
+// How ranges that share IDs are counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Overlap {
+    // merge overlapping ranges first, so every ID counts once
+    Merge,
+    // sum every range on its own, IDs in several ranges count several times
+    PerRange,
+}
+
+fn parse_ranges(content: &str, overlap: Overlap) -> Result<Vec<(u128, u128)>> {
+    let mut ranges = content
+        .lines()
+        .flat_map(|line| line.split(","))
+        .map(parse_range)
+        .collect::<Result<Vec<_>>>()?;
+    if overlap == Overlap::PerRange {
+        return Ok(ranges);
+    }
+    ranges.sort();
+    let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
         }
     }
-    Ok(sum)
+    Ok(merged)
 }
 
-fn solve_str<F>(content: &str, is_valid_id: F) -> Result<i64>
+fn solve_str<F>(content: &str, overlap: Overlap, sum_range: F) -> Result<u128>
 where
-    F: Fn(i64) -> bool + Clone,
+    F: Fn(u128, u128) -> Result<u128>,
 {
-    content.lines().try_fold(0, |acc, line| {
-        let sum: i64 = line
-            .split(",")
-            .map(|s| sum_invalid_ids(s, is_valid_id.clone()))
-            .collect::<Result<Vec<_>, _>>()?
-            .iter()
-            .sum();
-        Ok(acc + sum)
+    parse_ranges(content, overlap)?
+        .into_iter()
+        .try_fold(0u128, |acc, (start, end)| {
+            acc.checked_add(sum_range(start, end)?)
+                .context("total sum of invalid ids overflows")
+        })
+}
+
+struct Options {
+    rules: Rules,
+    overlap: Overlap,
+    threads: usize,
+    explain: Option<explain::Format>,
+    limit: usize,
+}
+
+// [--rule R]... [--radix N] [--per-range] [--threads N] [--explain [--json] [--limit N]]
+fn parse_options(default: Rules, flags: &[String]) -> Result<Options> {
+    let (mut rules, mut radix) = (Vec::new(), 10);
+    let (mut explain, mut json, mut limit) = (false, false, 10);
+    let mut overlap = Overlap::Merge;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let mut value = || {
+            flags
+                .next()
+                .with_context(|| format!("missing value for {}", flag))
+        };
+        match flag.as_str() {
+            "--rule" => rules.push(Rule::try_from(value()?.as_str())?),
+            "--radix" => radix = value()?.parse().context("invalid --radix")?,
+            "--per-range" => overlap = Overlap::PerRange,
+            "--threads" => match value()?.parse().context("invalid --threads")? {
+                0 => anyhow::bail!("--threads must be positive"),
+                n => threads = n,
+            },
+            "--explain" => explain = true,
+            "--json" => json = true,
+            "--limit" => limit = value()?.parse().context("invalid --limit")?,
+            s => anyhow::bail!("unexpected flag: {}", s),
+        }
+    }
+    let rules = match (rules.is_empty(), radix) {
+        (true, 10) => default,
+        (true, _) => anyhow::bail!("--radix needs at least one --rule"),
+        (false, _) => Rules::new(radix, rules)?,
+    };
+    let explain = explain.then_some(match json {
+        true => explain::Format::Json,
+        false => explain::Format::Text,
+    });
+    Ok(Options {
+        rules,
+        overlap,
+        threads,
+        explain,
+        limit,
     })
 }
 
+// Explains the ranges as written in the input, whether or not the solution
+// merges overlapping ones.
+fn explain_str(content: &str, options: &Options, format: explain::Format) -> Result<String> {
+    let ranges = parse_ranges(content, Overlap::PerRange)?;
+    let explained = explain::explain(&ranges, &options.rules, options.limit)?;
+    format.render(&explained)
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     let path = args.get(1).context("missing file path argument")?;
-    match args.get(2).map(|s| s.as_str()).context("missing part")? {
-        "part1" => {
-            println!("solution: {:#?}", solve_file(path, part1)?)
-        }
-        "part2" => {
-            println!("solution: {:#?}", solve_file(path, part2)?)
-        }
+    let rules = match args.get(2).map(|s| s.as_str()).context("missing part")? {
+        "part1" => Rules::part1(),
+        "part2" => Rules::part2(),
         s => anyhow::bail!("unexpected part: {}", s),
     };
+    let options = parse_options(rules, &args[3..])?;
+    if let Some(format) = options.explain {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        print!("{}", explain_str(&content, &options, format)?);
+        return Ok(());
+    }
+    println!("solution: {:#?}", solve_file(path, &options)?);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use difftest::{Harness, Rng, Suite};
+    fn part1(start: u128, end: u128) -> Result<u128> {
+        sum_invalid(&Rules::part1(), start, end)
+    }
+
+    fn part2(start: u128, end: u128) -> Result<u128> {
+        sum_invalid(&Rules::part2(), start, end)
+    }
+
+    fn scan(start: u128, end: u128, is_valid_id: fn(i64) -> bool) -> Result<u128> {
+        Ok(sum_invalid_ids(start as i64, end as i64, is_valid_id) as u128)
+    }
+
     const INPUT: &str = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
 
     #[test]
     fn test_1() {
-        let actual = solve_str(INPUT, part1).expect("must solve");
+        let actual = solve_str(INPUT, Overlap::Merge, part1).expect("must solve");
         assert_eq!(1227775554, actual);
     }
     #[test]
     fn test_part_2() {
-        let actual = solve_str(INPUT, part2).expect("must solve");
+        let actual = solve_str(INPUT, Overlap::Merge, part2).expect("must solve");
         assert_eq!(4174379265, actual);
     }
+
+    #[test]
+    fn test_scan() {
+        let actual = solve_str(INPUT, Overlap::PerRange, |s, e| scan(s, e, valid_part1));
+        assert_eq!(1227775554, actual.expect("must solve"));
+        let actual = solve_str(INPUT, Overlap::PerRange, |s, e| scan(s, e, valid_part2));
+        assert_eq!(4174379265, actual.expect("must solve"));
+    }
+
+    // Range of up to a few thousand IDs starting anywhere below 10^12.
+    fn generate(rng: &mut Rng) -> (u64, u64) {
+        let digits = rng.range(1, 12) as u32;
+        let start = rng.range(0, 10u64.pow(digits));
+        (start, rng.range(0, 3_000))
+    }
+
+    fn range_of((start, len): &(u64, u64)) -> (u128, u128) {
+        (*start as u128, (start + len) as u128)
+    }
+
+    #[test]
+    fn test_enumeration_matches_scan() {
+        let ids = |range: &(u64, u64), times: fn(u32) -> bool| {
+            let (s, e) = range_of(range);
+            repeated::repeated_ids(s, e, 10, times).collect::<Vec<_>>()
+        };
+        let scan_ids = |range: &(u64, u64), is_valid_id: fn(i64) -> bool| {
+            let (s, e) = range_of(range);
+            (s..=e)
+                .filter(|&id| !is_valid_id(id as i64))
+                .collect::<Vec<_>>()
+        };
+        Suite::new(Harness::new())
+            .register(
+                "part1",
+                generate,
+                move |r| scan(range_of(r).0, range_of(r).1, valid_part1).unwrap(),
+                move |r| part1(range_of(r).0, range_of(r).1).unwrap(),
+            )
+            .register(
+                "part2",
+                generate,
+                move |r| scan(range_of(r).0, range_of(r).1, valid_part2).unwrap(),
+                move |r| part2(range_of(r).0, range_of(r).1).unwrap(),
+            )
+            .register(
+                "part1 ids",
+                generate,
+                move |r| scan_ids(r, valid_part1),
+                move |r| ids(r, |times| times.is_multiple_of(2)),
+            )
+            .register(
+                "part2 ids",
+                generate,
+                move |r| scan_ids(r, valid_part2),
+                move |r| ids(r, |times| times >= 2),
+            )
+            .run();
+    }
+
+    #[test]
+    fn test_huge_range() {
+        // 2-digit..18-digit IDs, far too many to scan
+        let (count, _) =
+            repeated::sum_repeated(10, 999_999_999_999_999_999, 10, |t| t.is_multiple_of(2))
+                .unwrap();
+        assert_eq!(999_999_999, count);
+        let (count, sum) = repeated::sum_repeated(1, 99, 10, |t| t >= 2).unwrap();
+        assert_eq!((9, 495), (count, sum));
+        let (count, sum) = repeated::sum_repeated(1, 9999, 10, |t| t >= 2).unwrap();
+        let ids: Vec<u128> = repeated::repeated_ids(1, 9999, 10, |t| t >= 2).collect();
+        assert_eq!(count, ids.len() as u128);
+        assert_eq!(sum, ids.iter().sum::<u128>());
+
+        // capped listings of a wide 24-digit range stay cheap
+        let (start, end) = (10u128.pow(23), 10u128.pow(24) - 1);
+        let ranges = explain::explain(&[(start, end)], &Rules::part2(), 3).unwrap();
+        let expected: Vec<u128> = (0..3)
+            .map(|b| (100_000_000_000 + b) * 1_000_000_000_001)
+            .collect();
+        assert_eq!(expected, ranges[0].ids);
+        assert_eq!(ranges[0].count - 3, ranges[0].more);
+        let ids: Vec<u128> = repeated::repeated_ids(start, end, 10, |t| t >= 2)
+            .take(10_000)
+            .collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert!(ids.iter().all(|&id| repeated::period(id, 10) < 24));
+    }
+
+    #[test]
+    fn test_explain() {
+        let ranges = parse_ranges(INPUT, Overlap::PerRange).unwrap();
+        let ranges = explain::explain(&ranges, &Rules::part2(), 2).expect("must explain");
+        assert_eq!(11, ranges.len());
+        assert_eq!(4174379265, ranges.iter().map(|r| r.sum).sum::<u128>());
+        let r = &ranges[2];
+        assert_eq!((998, 1012, 2, 2009), (r.start, r.end, r.count, r.sum));
+        assert_eq!(vec![999, 1010], r.ids);
+
+        let text = explain::Format::Text.render(&ranges).unwrap();
+        assert!(text.starts_with(
+            "11-22: 2 invalid, sum 33: 11, 22\n95-115: 2 invalid, sum 210: 99, 111\n"
+        ));
+        let ranges = explain::explain(&[(1, 1000)], &Rules::part2(), 3).unwrap();
+        let text = explain::Format::Text.render(&ranges).unwrap();
+        assert_eq!(
+            "1-1000: 18 invalid, sum 5490: 11, 22, 33 …and 15 more\n",
+            text
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&explain::Format::Json.render(&ranges).unwrap()).unwrap();
+        assert_eq!(json[0]["ids"], serde_json::json!([11, 22, 33]));
+        assert_eq!(json[0]["more"], 15);
+
+        // overlapping ranges are explained as given, not merged
+        let options = parse_options(Rules::part2(), &[]).unwrap();
+        assert_eq!(Overlap::Merge, options.overlap);
+        let text = explain_str("10-30,20-40", &options, explain::Format::Text).unwrap();
+        assert_eq!(
+            "10-30: 2 invalid, sum 33: 11, 22\n20-40: 2 invalid, sum 55: 22, 33\n",
+            text
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let flags = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        let options = parse_options(Rules::part1(), &flags("--rule palindrome")).unwrap();
+        // 1..9, 11, 22, ..., 99
+        assert_eq!(Some((18, 540)), options.rules.sum(1, 99));
+        let options = parse_options(
+            Rules::part1(),
+            &flags("--rule repeat=2 --rule digit-sum%4 --radix 2"),
+        )
+        .unwrap();
+        // 0b11, 0b1010, 0b1111, 0b100100, 0b101101, ...: only 15 has 4 ones below 36
+        assert_eq!(vec![15], options.rules.ids(0, 35).collect::<Vec<_>>());
+        let options = parse_options(Rules::part2(), &flags("--rule repeat>=3 --radix 16")).unwrap();
+        assert_eq!(
+            vec![0x111, 0x888, 0xfff],
+            options
+                .rules
+                .ids(0x100, 0x1000)
+                .step_by(7)
+                .collect::<Vec<_>>()
+        );
+        assert!(parse_options(Rules::part1(), &flags("--radix 16")).is_err());
+        assert!(parse_options(Rules::part1(), &flags("--rule repeat=0")).is_err());
+        assert!(parse_options(Rules::part1(), &flags("--rule digits=3")).is_err());
+        assert!(parse_options(Rules::part1(), &flags("--rule palindrome --radix 1")).is_err());
+    }
+
+    #[test]
+    fn test_rules_match_scan() {
+        const RULES: [&str; 7] = [
+            "repeat=2",
+            "repeat=3",
+            "repeat>=2",
+            "repeat>=3",
+            "palindrome",
+            "digit-sum=9",
+            "digit-sum%3",
+        ];
+        // (rule indexes, radix index, (start, len))
+        type Input = (Vec<u8>, u8, (u64, u64));
+        let rules_of = |(picked, radix, _): &Input| {
+            let rules = picked
+                .iter()
+                .map(|&i| Rule::try_from(RULES[i as usize % RULES.len()]).unwrap())
+                .collect();
+            Rules::new([10, 2, 8, 16][*radix as usize % 4], rules).unwrap()
+        };
+        let generate = |rng: &mut Rng| -> Input {
+            let picked = (0..rng.range(1, 2))
+                .map(|_| rng.below(RULES.len()) as u8)
+                .collect();
+            (picked, rng.below(4) as u8, generate(rng))
+        };
+        Harness::new().cases(500).check(
+            "rules",
+            generate,
+            move |input| {
+                let (s, e) = range_of(&input.2);
+                let rules = rules_of(input);
+                (s..=e)
+                    .filter(|&id| rules.is_invalid(id))
+                    .fold((0, 0), |(c, sum), id| (c + 1, sum + id))
+            },
+            move |input| {
+                let (s, e) = range_of(&input.2);
+                rules_of(input).sum(s, e).unwrap()
+            },
+        );
+    }
+
+    #[test]
+    fn test_overlaps_and_bounds() {
+        // 11, 22 and 33, with 22 in both ranges
+        assert_eq!(66, solve_str("10-30,20-40", Overlap::Merge, part1).unwrap());
+        assert_eq!(
+            88,
+            solve_str("10-30,20-40", Overlap::PerRange, part1).unwrap()
+        );
+        assert_eq!(
+            vec![(1, 5), (7, 12)],
+            parse_ranges("7-8,1-3\n4-5,8-12,9-10", Overlap::Merge).unwrap()
+        );
+
+        let err = solve_str("11-22,20-10", Overlap::Merge, part1).unwrap_err();
+        assert_eq!(
+            "reversed range \"20-10\": start must not exceed end",
+            err.to_string()
+        );
+        assert!(solve_str("-5-10", Overlap::Merge, part1).is_err());
+
+        // 38 nines is 19 nines twice; beyond i64 and close to u128::MAX
+        let nines = "9".repeat(38);
+        let input = format!("{}-{}", nines, nines);
+        assert_eq!(
+            nines.parse::<u128>().unwrap(),
+            solve_str(&input, Overlap::Merge, part1).unwrap()
+        );
+        let max = u128::MAX;
+        let input = format!("{}-{}", max - 10_000, max);
+        assert_eq!(0, solve_str(&input, Overlap::Merge, part2).unwrap());
+        let input = format!("1-{}", max);
+        let err = solve_str(&input, Overlap::Merge, part2).unwrap_err();
+        assert!(err.to_string().ends_with("overflows"));
+    }
+
+    #[test]
+    fn test_threads_match_single_thread() {
+        const RULES: [&str; 4] = ["repeat=2", "repeat>=2", "palindrome", "digit-sum%7"];
+        // (rule index, threads, ranges as (start, len))
+        type Input = (u8, u8, Vec<(u64, u64)>);
+        let setup = |(rule, _, ranges): &Input| {
+            let rules = Rules::new(10, vec![Rule::try_from(RULES[*rule as usize % 4]).unwrap()]);
+            let content: Vec<String> = ranges
+                .iter()
+                .map(|r| format!("{}-{}", range_of(r).0, range_of(r).1))
+                .collect();
+            (rules.unwrap(), content.join(","))
+        };
+        Harness::new().cases(50).check(
+            "threads",
+            |rng| {
+                let ranges = (0..rng.range(1, 6))
+                    .map(|_| (rng.range(0, 1_000_000), rng.range(0, 12_000)))
+                    .collect();
+                (rng.below(4) as u8, rng.range(2, 8) as u8, ranges)
+            },
+            move |input| {
+                let (rules, content) = setup(input);
+                solve_str(&content, Overlap::Merge, |s, e| sum_invalid(&rules, s, e)).unwrap()
+            },
+            move |input| {
+                let (rules, content) = setup(input);
+                let ranges = parse_ranges(&content, Overlap::Merge).unwrap();
+                parallel::sum_ranges(&ranges, &rules, input.1.max(2) as usize).unwrap()
+            },
+        );
+        let ranges = parse_ranges(INPUT, Overlap::Merge).unwrap();
+        assert_eq!(
+            4174379265,
+            parallel::sum_ranges(&ranges, &Rules::part2(), 3).unwrap()
+        );
+    }
 }
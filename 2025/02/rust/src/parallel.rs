@@ -0,0 +1,71 @@
+use crate::rules::Rules;
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Smallest piece of a range worth handing to a thread.
+const MIN_CHUNK: u128 = 1 << 12;
+
+// Ranges summed arithmetically take constant time and are kept whole; the
+// ones that have to be scanned are cut so every thread gets several pieces.
+fn chunks(ranges: &[(u128, u128)], rules: &Rules, threads: usize) -> Vec<(u128, u128)> {
+    if rules.is_arithmetic() {
+        return ranges.to_vec();
+    }
+    let width = ranges.iter().fold(0u128, |acc, (start, end)| {
+        acc.saturating_add(end - start + 1)
+    });
+    let size = (width / (threads as u128 * 4)).max(MIN_CHUNK);
+    ranges
+        .iter()
+        .flat_map(|&(start, end)| {
+            let mut pieces = Vec::new();
+            let mut lo = start;
+            loop {
+                let hi = lo.saturating_add(size - 1).min(end);
+                pieces.push((lo, hi));
+                if hi == end {
+                    break pieces;
+                }
+                lo = hi + 1;
+            }
+        })
+        .collect()
+}
+
+// Sums invalid IDs over `threads` threads. Pieces are added up in input
+// order, so the result and any overflow error are the same on every run.
+pub fn sum_ranges(ranges: &[(u128, u128)], rules: &Rules, threads: usize) -> Result<u128> {
+    let chunks = chunks(ranges, rules, threads);
+    let mut sums: Vec<Option<u128>> = vec![None; chunks.len()];
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(chunks.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(start, end)) = chunks.get(i) else {
+                            break done;
+                        };
+                        done.push((i, rules.sum(start, end).map(|(_, sum)| sum)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (i, sum) in worker.join().expect("worker must not panic") {
+                sums[i] = sum;
+            }
+        }
+    });
+    sums.iter()
+        .zip(&chunks)
+        .try_fold(0u128, |acc, (sum, (start, end))| {
+            let sum =
+                sum.with_context(|| format!("sum of invalid ids in {}-{} overflows", start, end))?;
+            acc.checked_add(sum)
+                .context("total sum of invalid ids overflows")
+        })
+}
@@ -0,0 +1,185 @@
+// IDs made of a digit block repeated several times, e.g. 1212 or 777.
+//
+// An ID with `digits` digits whose shortest repeating block has `period`
+// digits is that block repeated `digits / period` times; it can also be read
+// as any divisor of that count repetitions of a longer block (121212 is
+// 12 x3 but not 121 x2). Callers decide which repetition counts make an ID
+// invalid with a `times` predicate over that maximal count.
+//
+// Digits are taken in `radix`, so 0b1010 is "10" repeated twice. Sums that
+// don't fit into u128 come back as None.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// Smallest and largest numbers with `n` digits; n never exceeds the digits
+// of u128::MAX, so only the upper bound can overflow.
+fn smallest(n: u32, radix: u128) -> u128 {
+    radix.pow(n - 1)
+}
+
+fn largest(n: u32, radix: u128) -> u128 {
+    radix.checked_pow(n).map_or(u128::MAX, |p| p - 1)
+}
+
+// Multiplier turning a `block`-digit block into `digits` digits, e.g. 10101
+// for 2-digit blocks in 6-digit numbers.
+fn repunit(digits: u32, block: u32, radix: u128) -> Option<u128> {
+    (0..digits / block).try_fold(0u128, |acc, _| {
+        acc.checked_mul(radix.checked_pow(block)?)?.checked_add(1)
+    })
+}
+
+// Sum of all integers in [lo, hi], lo <= hi.
+fn series(lo: u128, hi: u128) -> Option<u128> {
+    let count = hi - lo + 1;
+    // one of (lo + hi) and count is even
+    match count.is_multiple_of(2) {
+        true => (count / 2).checked_mul(lo.checked_add(hi)?),
+        false => (lo.checked_add(hi)? / 2).checked_mul(count),
+    }
+}
+
+// Numbers in [start, end] with `digits` digits made of any `block`-digit
+// block repeated, as (count, sum).
+fn block_sum(start: u128, end: u128, digits: u32, block: u32, radix: u128) -> Option<(u128, u128)> {
+    let repunit = repunit(digits, block, radix)?;
+    let lo = start.div_ceil(repunit).max(smallest(block, radix));
+    let hi = (end / repunit).min(largest(block, radix));
+    if lo > hi {
+        return Some((0, 0));
+    }
+    Some((hi - lo + 1, repunit.checked_mul(series(lo, hi)?)?))
+}
+
+fn divisors(n: u32) -> Vec<u32> {
+    (1..=n).filter(|d| n.is_multiple_of(*d)).collect()
+}
+
+pub fn digits(n: u128, radix: u128) -> u32 {
+    n.checked_ilog(radix).unwrap_or(0) + 1
+}
+
+fn bounds(start: u128, end: u128, d: u32, radix: u128) -> Option<(u128, u128)> {
+    let lo = start.max(smallest(d, radix));
+    let hi = end.min(largest(d, radix));
+    (lo <= hi).then_some((lo, hi))
+}
+
+// Count and sum of IDs in [start, end] whose maximal repetition count
+// satisfies `times`. Block sums overlap (1111 is counted for blocks 1, 11
+// and 1111), so exact sums per shortest period come from inclusion–exclusion:
+// exact(p) = block(p) - sum of exact(q) for q dividing p.
+pub fn sum_repeated<F>(start: u128, end: u128, radix: u128, times: F) -> Option<(u128, u128)>
+where
+    F: Fn(u32) -> bool,
+{
+    let (mut count, mut sum) = (0u128, 0u128);
+    for d in digits(start, radix)..=digits(end, radix) {
+        let Some((lo, hi)) = bounds(start, end, d, radix) else {
+            continue;
+        };
+        let periods = divisors(d);
+        let wanted: Vec<u32> = periods.iter().copied().filter(|p| times(d / p)).collect();
+        let mut exact: Vec<(u128, u128)> = Vec::with_capacity(periods.len());
+        for (i, &p) in periods.iter().enumerate() {
+            // periods no wanted period is a multiple of don't matter, and
+            // skipping them avoids overflowing on sums that aren't needed
+            if !wanted.iter().any(|w| w.is_multiple_of(p)) {
+                exact.push((0, 0));
+                continue;
+            }
+            let (mut c, mut s) = block_sum(lo, hi, d, p, radix)?;
+            for (j, &q) in periods[..i].iter().enumerate() {
+                if p.is_multiple_of(q) {
+                    c -= exact[j].0;
+                    s -= exact[j].1;
+                }
+            }
+            exact.push((c, s));
+            if wanted.contains(&p) {
+                count += c;
+                sum = sum.checked_add(s)?;
+            }
+        }
+    }
+    Some((count, sum))
+}
+
+// Digits of `n` in `radix`, most significant first.
+pub fn to_digits(mut n: u128, radix: u128) -> Vec<u8> {
+    let mut out = vec![(n % radix) as u8];
+    while n >= radix {
+        n /= radix;
+        out.push((n % radix) as u8);
+    }
+    out.reverse();
+    out
+}
+
+// Shortest block length the representation of `n` is made of.
+pub fn period(n: u128, radix: u128) -> u32 {
+    let s = to_digits(n, radix);
+    (1..=s.len())
+        .filter(|p| s.len().is_multiple_of(*p))
+        .find(|&p| s.chunks(p).all(|c| c == &s[..p]))
+        .unwrap_or(s.len()) as u32
+}
+
+// Ascending merge of ascending iterators that never yield the same value.
+struct Merge<I> {
+    sources: Vec<I>,
+    heads: BinaryHeap<Reverse<(u128, usize)>>,
+}
+
+impl<I: Iterator<Item = u128>> Merge<I> {
+    fn new(mut sources: Vec<I>) -> Self {
+        let heads = sources
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, source)| Some(Reverse((source.next()?, i))))
+            .collect();
+        Self { sources, heads }
+    }
+}
+
+impl<I: Iterator<Item = u128>> Iterator for Merge<I> {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        let Reverse((n, i)) = self.heads.pop()?;
+        if let Some(next) = self.sources[i].next() {
+            self.heads.push(Reverse((next, i)));
+        }
+        Some(n)
+    }
+}
+
+// Enumerates matching IDs in ascending order, visiting only candidates built
+// from blocks. Each period yields its candidates in order and they are merged
+// lazily, so taking the first few IDs is cheap even for huge ranges.
+pub fn repeated_ids<F>(start: u128, end: u128, radix: u128, times: F) -> impl Iterator<Item = u128>
+where
+    F: Fn(u32) -> bool,
+{
+    (digits(start, radix)..=digits(end, radix)).flat_map(move |d| {
+        let sources = bounds(start, end, d, radix)
+            .into_iter()
+            .flat_map(|(lo, hi)| {
+                let periods = divisors(d).into_iter().filter(|p| times(d / p));
+                periods.filter_map(move |p| {
+                    let repunit = repunit(d, p, radix)?;
+                    let first = lo.div_ceil(repunit).max(smallest(p, radix));
+                    let last = (hi / repunit).min(largest(p, radix));
+                    // skip blocks that are repetitions themselves, they belong to
+                    // a shorter period and were (or weren't) emitted there
+                    Some(
+                        (first..=last)
+                            .map(move |b| b * repunit)
+                            .filter(move |&n| period(n, radix) == p),
+                    )
+                })
+            });
+        Merge::new(sources.collect())
+    })
+}
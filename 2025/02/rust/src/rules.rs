@@ -0,0 +1,148 @@
+use crate::repeated;
+use anyhow::{Context, Result, anyhow};
+
+// A property of an ID's digits; an ID is invalid when every rule holds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    // digits are a block repeated exactly k times: repeat=k
+    RepeatExactly(u32),
+    // digits are a block repeated k or more times: repeat>=k
+    RepeatAtLeast(u32),
+    // digits read the same backwards: palindrome
+    Palindrome,
+    // digits add up to n: digit-sum=n
+    DigitSum(u32),
+    // digits add up to a multiple of n: digit-sum%n
+    DigitSumMultipleOf(u32),
+}
+
+impl TryFrom<&str> for Rule {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let number = |v: &str| -> Result<u32> {
+            v.parse()
+                .with_context(|| format!("invalid number in rule {:?}", s))
+        };
+        let rule = if s == "palindrome" {
+            Rule::Palindrome
+        } else if let Some(k) = s.strip_prefix("repeat>=") {
+            Rule::RepeatAtLeast(number(k)?)
+        } else if let Some(k) = s.strip_prefix("repeat=") {
+            Rule::RepeatExactly(number(k)?)
+        } else if let Some(n) = s.strip_prefix("digit-sum=") {
+            Rule::DigitSum(number(n)?)
+        } else if let Some(n) = s.strip_prefix("digit-sum%") {
+            Rule::DigitSumMultipleOf(number(n)?)
+        } else {
+            anyhow::bail!("unexpected rule: {}", s);
+        };
+        match rule {
+            Rule::RepeatExactly(0) | Rule::RepeatAtLeast(0) | Rule::DigitSumMultipleOf(0) => {
+                Err(anyhow!("rule {:?} needs a positive number", s))
+            }
+            rule => Ok(rule),
+        }
+    }
+}
+
+impl Rule {
+    fn is_repeat(&self) -> bool {
+        matches!(self, Rule::RepeatExactly(_) | Rule::RepeatAtLeast(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    radix: u128,
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new(radix: u32, rules: Vec<Rule>) -> Result<Self> {
+        if !(2..=36).contains(&radix) {
+            anyhow::bail!("radix must be within 2..=36, got {}", radix);
+        }
+        Ok(Self {
+            radix: radix as u128,
+            rules,
+        })
+    }
+
+    pub fn part1() -> Self {
+        Self {
+            radix: 10,
+            rules: vec![Rule::RepeatExactly(2)],
+        }
+    }
+
+    pub fn part2() -> Self {
+        Self {
+            radix: 10,
+            rules: vec![Rule::RepeatAtLeast(2)],
+        }
+    }
+
+    // Repetition rules only depend on the maximal repetition count, which
+    // is what the arithmetic enumeration works with.
+    fn times(&self, times: u32) -> bool {
+        self.rules.iter().all(|rule| match rule {
+            Rule::RepeatExactly(k) => times.is_multiple_of(*k),
+            Rule::RepeatAtLeast(k) => times >= *k,
+            _ => true,
+        })
+    }
+
+    fn has_repeat(&self) -> bool {
+        self.rules.iter().any(Rule::is_repeat)
+    }
+
+    // Only repetition rules: sums come from arithmetic, not from visiting IDs.
+    pub fn is_arithmetic(&self) -> bool {
+        self.has_repeat() && self.rules.iter().all(Rule::is_repeat)
+    }
+
+    fn digit_rules_hold(&self, id: u128) -> bool {
+        let digits = repeated::to_digits(id, self.radix);
+        self.rules.iter().all(|rule| match rule {
+            Rule::Palindrome => digits.iter().eq(digits.iter().rev()),
+            Rule::DigitSum(n) => digits.iter().map(|&d| d as u32).sum::<u32>() == *n,
+            Rule::DigitSumMultipleOf(n) => digits
+                .iter()
+                .map(|&d| d as u32)
+                .sum::<u32>()
+                .is_multiple_of(*n),
+            _ => true,
+        })
+    }
+
+    pub fn is_invalid(&self, id: u128) -> bool {
+        let digits = repeated::digits(id, self.radix);
+        self.times(digits / repeated::period(id, self.radix)) && self.digit_rules_hold(id)
+    }
+
+    // Invalid IDs in [start, end] in ascending order. With a repetition rule
+    // only repeated IDs are visited, otherwise every ID in the range is.
+    pub fn ids(&self, start: u128, end: u128) -> Box<dyn Iterator<Item = u128> + '_> {
+        if self.has_repeat() {
+            Box::new(
+                repeated::repeated_ids(start, end, self.radix, |t| self.times(t))
+                    .filter(|&id| self.digit_rules_hold(id)),
+            )
+        } else {
+            Box::new((start..=end).filter(|&id| self.is_invalid(id)))
+        }
+    }
+
+    // Count and sum of invalid IDs in [start, end], None if the sum
+    // doesn't fit into u128.
+    pub fn sum(&self, start: u128, end: u128) -> Option<(u128, u128)> {
+        if self.is_arithmetic() {
+            return repeated::sum_repeated(start, end, self.radix, |t| self.times(t));
+        }
+        self.ids(start, end)
+            .try_fold((0, 0u128), |(count, sum), id| {
+                Some((count + 1, sum.checked_add(id)?))
+            })
+    }
+}
@@ -0,0 +1,420 @@
+use anyhow::{Context, Result};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Restrictions on which batteries of a bank may be turned on together.
+// `min_gap` is the smallest index distance between consecutive picks,
+// `max_span` the largest distance between the first and the last one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraints {
+    pub min_gap: usize,
+    pub max_span: Option<usize>,
+    pub excluded: [bool; 10],
+    pub max_repeat: Option<usize>,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints {
+            min_gap: 1,
+            max_span: None,
+            excluded: [false; 10],
+            max_repeat: None,
+        }
+    }
+}
+
+impl Constraints {
+    pub fn set(&mut self, flag: &str, value: &str) -> Result<()> {
+        let number = || -> Result<usize> {
+            value
+                .parse()
+                .with_context(|| format!("invalid {} {:?}", flag, value))
+        };
+        match flag {
+            "--min-gap" => match number()? {
+                0 => anyhow::bail!("--min-gap must be positive"),
+                n => self.min_gap = n,
+            },
+            "--max-span" => self.max_span = Some(number()?),
+            "--max-repeat" => self.max_repeat = Some(number()?),
+            "--exclude" => {
+                for ch in value.chars() {
+                    let digit = ch
+                        .to_digit(10)
+                        .with_context(|| format!("invalid --exclude digit {:?}", ch))?;
+                    self.excluded[digit as usize] = true;
+                }
+            }
+            s => anyhow::bail!("unexpected flag: {}", s),
+        }
+        Ok(())
+    }
+
+    // The constraints with the ones that can't matter for `count` picks from
+    // `l` dropped, so that those banks take the linear selection.
+    fn effective(&self, l: &[u32], count: usize) -> Constraints {
+        let mut excluded = [false; 10];
+        for &digit in l {
+            excluded[digit as usize] = self.excluded[digit as usize];
+        }
+        Constraints {
+            min_gap: self.min_gap,
+            max_span: self
+                .max_span
+                .filter(|&span| span < l.len().saturating_sub(1)),
+            excluded,
+            max_repeat: self.max_repeat.filter(|&cap| cap < count),
+        }
+    }
+
+    // Indices of the largest `count`-digit selection that satisfies the
+    // constraints, None when there is none.
+    pub fn select(&self, l: &[u32], count: usize) -> Option<Vec<usize>> {
+        let c = self.effective(l, count);
+        if c == Constraints::default() {
+            return crate::select(l, count);
+        }
+        if count == 0 {
+            return None;
+        }
+        let mut search = Search::new(&c, l, count);
+        let Some(span) = c.max_span else {
+            return search.run(&[], l.len());
+        };
+        // Only the first pick fixes the window; later picks take the earliest
+        // occurrence of a digit since that leaves the most room behind it.
+        // Windows go from the last to the first, so failures found with a
+        // later window end carry over to the earlier, shorter ones.
+        // A window only counts when it beats the best of the later ones.
+        for digit in (0..10).rev() {
+            let mut best: Option<Vec<usize>> = None;
+            for i in (0..l.len()).rev().filter(|&i| l[i] == digit) {
+                search.beat = best.iter().flatten().map(|&i| l[i]).collect();
+                if let Some(picked) = search.run(&[i], (i + span + 1).min(l.len())) {
+                    best = Some(picked);
+                }
+            }
+            if best.is_some() {
+                return best;
+            }
+        }
+        None
+    }
+}
+
+// The index the next pick starts from, the picks left and the digits used so
+// far (all zero without a repeat cap).
+type Key = (usize, usize, [usize; 10]);
+
+struct Search<'a> {
+    constraints: &'a Constraints,
+    l: &'a [u32],
+    count: usize,
+    // next[i][d] is the first index >= i holding digit d
+    next: Vec<[usize; 10]>,
+    // seen[i][d] is the number of batteries before i holding digit d
+    seen: Vec<[usize; 10]>,
+    end: usize,
+    used: [usize; 10],
+    picked: Vec<usize>,
+    // digits a selection must beat to count, empty for any selection; `tied`
+    // is how many of the picks so far equal its leading digits
+    beat: Vec<u32>,
+    tied: usize,
+    // largest window end each failed state has been seen to fail with; it
+    // fails with every shorter window too
+    failed: HashMap<Key, usize>,
+}
+
+impl<'a> Search<'a> {
+    fn new(constraints: &'a Constraints, l: &'a [u32], count: usize) -> Self {
+        let mut next = vec![[l.len(); 10]; l.len() + 1];
+        for i in (0..l.len()).rev() {
+            next[i] = next[i + 1];
+            next[i][l[i] as usize] = i;
+        }
+        let mut seen = vec![[0; 10]; l.len() + 1];
+        for (i, &digit) in l.iter().enumerate() {
+            seen[i + 1] = seen[i];
+            seen[i + 1][digit as usize] += 1;
+        }
+        Search {
+            constraints,
+            l,
+            count,
+            next,
+            seen,
+            end: l.len(),
+            used: [0; 10],
+            picked: Vec::with_capacity(count),
+            beat: Vec::new(),
+            tied: 0,
+            failed: HashMap::new(),
+        }
+    }
+
+    fn allowed(&self, digit: usize) -> bool {
+        !self.constraints.excluded[digit]
+            && self
+                .constraints
+                .max_repeat
+                .is_none_or(|cap| self.used[digit] < cap)
+    }
+
+    // Best selection starting with `prefix` and lying entirely before `end`,
+    // None when there is none or it does not beat `beat`.
+    fn run(&mut self, prefix: &[usize], end: usize) -> Option<Vec<usize>> {
+        self.end = end;
+        self.used = [0; 10];
+        self.picked.clear();
+        self.tied = 0;
+        for &i in prefix {
+            if !self.allowed(self.l[i] as usize) || !self.pick(i) {
+                return None;
+            }
+        }
+        let from = prefix.last().map_or(0, |&i| i + self.constraints.min_gap);
+        self.extend(from).then(|| self.picked.clone())
+    }
+
+    // Depth-first search for the largest completion of `picked`, with an
+    // explicit stack since selections can be far longer than the call stack
+    // allows. A frame is the key of a state and the digit tried last there.
+    // Selections come up largest first, so the search stops at the first
+    // prefix below `beat`; that is not a failure of the states on the stack.
+    fn extend(&mut self, from: usize) -> bool {
+        let mut stack: Vec<(Key, usize)> = Vec::new();
+        let mut enter = Some(from);
+        loop {
+            if let Some(from) = enter.take() {
+                let remaining = self.count - self.picked.len();
+                if remaining == 0 {
+                    return self.beat.is_empty() || self.tied < self.count;
+                }
+                let used = match self.constraints.max_repeat {
+                    Some(_) => self.used,
+                    None => [0; 10],
+                };
+                let key = (from, remaining, used);
+                let fits = from + (remaining - 1) * self.constraints.min_gap < self.end
+                    && self.capacity(from) >= remaining;
+                let failed = self.failed.get(&key).is_some_and(|&end| end >= self.end);
+                if fits && !failed {
+                    stack.push((key, 10));
+                } else if stack.is_empty() {
+                    return false;
+                } else {
+                    self.unpick();
+                }
+            }
+            let Some(((from, _, _), tried)) = stack.last_mut() else {
+                return false;
+            };
+            let from = *from;
+            let digit = (0..*tried)
+                .rev()
+                .find(|&d| self.next[from][d] < self.end && self.allowed(d));
+            match digit {
+                Some(digit) => {
+                    *tried = digit;
+                    let i = self.next[from][digit];
+                    if !self.pick(i) {
+                        return false;
+                    }
+                    enter = Some(i + self.constraints.min_gap);
+                }
+                None => {
+                    let (key, _) = stack.pop().unwrap();
+                    let end = self.failed.entry(key).or_default();
+                    *end = self.end.max(*end);
+                    if stack.is_empty() {
+                        return false;
+                    }
+                    self.unpick();
+                }
+            }
+        }
+    }
+
+    // How many more batteries from `from` on could be picked ignoring the
+    // gap: every allowed digit as often as it occurs, up to its repeat cap.
+    // With a gap of 1 that is exact, so the search never backtracks far.
+    fn capacity(&self, from: usize) -> usize {
+        let (lo, hi) = (&self.seen[from.min(self.end)], &self.seen[self.end]);
+        (0..10)
+            .filter(|&d| !self.constraints.excluded[d])
+            .map(|d| {
+                let left = self
+                    .constraints
+                    .max_repeat
+                    .map_or(usize::MAX, |cap| cap - self.used[d]);
+                (hi[d] - lo[d]).min(left)
+            })
+            .sum()
+    }
+
+    // Adds `i` to the selection, false when that puts it below `beat`.
+    fn pick(&mut self, i: usize) -> bool {
+        let digit = self.l[i];
+        let k = self.picked.len();
+        self.used[digit as usize] += 1;
+        self.picked.push(i);
+        if self.tied == k && k < self.beat.len() {
+            match digit.cmp(&self.beat[k]) {
+                Ordering::Less => return false,
+                Ordering::Equal => self.tied += 1,
+                Ordering::Greater => {}
+            }
+        }
+        true
+    }
+
+    fn unpick(&mut self) {
+        let i = self.picked.pop().unwrap();
+        self.used[self.l[i] as usize] -= 1;
+        self.tied = self.tied.min(self.picked.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use difftest::Harness;
+
+    // Every increasing `count`-subset of indices, largest valid digits win.
+    fn brute_force(c: &Constraints, l: &[u32], count: usize) -> Option<Vec<u32>> {
+        fn go(
+            c: &Constraints,
+            l: &[u32],
+            count: usize,
+            picked: &mut Vec<usize>,
+            best: &mut Option<Vec<u32>>,
+        ) {
+            if picked.len() == count {
+                let digits: Vec<u32> = picked.iter().map(|&i| l[i]).collect();
+                let span_ok = c
+                    .max_span
+                    .is_none_or(|s| picked[count - 1] - picked[0] <= s);
+                let repeat_ok = c.max_repeat.is_none_or(|cap| {
+                    (0..10).all(|d| digits.iter().filter(|&&x| x == d).count() <= cap)
+                });
+                if span_ok && repeat_ok && best.as_ref().is_none_or(|b| digits > *b) {
+                    *best = Some(digits);
+                }
+                return;
+            }
+            let from = picked.last().map_or(0, |&i| i + c.min_gap);
+            for i in from..l.len() {
+                if !c.excluded[l[i] as usize] {
+                    picked.push(i);
+                    go(c, l, count, picked, best);
+                    picked.pop();
+                }
+            }
+        }
+        let mut best = None;
+        if count > 0 {
+            go(c, l, count, &mut vec![], &mut best);
+        }
+        best
+    }
+
+    #[test]
+    fn test_constraints() {
+        let l = [8, 1, 8, 1, 8, 1, 9, 1, 1, 1, 1, 2, 1, 1, 1];
+        let digits = |c: &Constraints, count| {
+            c.select(&l, count)
+                .map(|p| p.iter().map(|&i| l[i]).collect::<Vec<_>>())
+        };
+        let mut c = Constraints::default();
+        assert_eq!(Some(vec![9, 2, 1, 1]), digits(&c, 4));
+        c.set("--exclude", "9").unwrap();
+        assert_eq!(Some(vec![8, 8, 8, 2]), digits(&c, 4));
+        c.set("--max-repeat", "2").unwrap();
+        assert_eq!(Some(vec![8, 8, 2, 1]), digits(&c, 4));
+        c.set("--min-gap", "3").unwrap();
+        assert_eq!(Some(vec![8, 8, 2, 1]), digits(&c, 4));
+        c.set("--max-span", "5").unwrap();
+        assert_eq!(None, digits(&c, 3));
+        assert!(c.set("--min-gap", "0").is_err());
+        assert!(c.set("--exclude", "x").is_err());
+    }
+
+    #[test]
+    fn test_long_selections() {
+        let mut rng = difftest::Rng::new(40);
+        let l: Vec<u32> = (0..100_000).map(|_| rng.range(1, 9) as u32).collect();
+        let mut c = Constraints::default();
+        c.set("--exclude", "0").unwrap();
+        c.set("--max-repeat", "60000").unwrap();
+        c.set("--max-span", "100000").unwrap();
+        assert_eq!(Constraints::default(), c.effective(&l, 60_000));
+        assert_eq!(crate::select(&l, 60_000), c.select(&l, 60_000));
+
+        // only exclusions: the linear selection over the other batteries
+        c.set("--exclude", "1").unwrap();
+        let kept: Vec<usize> = (0..l.len()).filter(|&i| l[i] != 1).collect();
+        let digits: Vec<u32> = kept.iter().map(|&i| l[i]).collect();
+        let expected = crate::select(&digits, 60_000).map(|p| p.iter().map(|&i| kept[i]).collect());
+        assert_eq!(expected, c.select(&l, 60_000));
+    }
+
+    #[test]
+    fn test_spans_with_repeat_caps() {
+        let mut rng = difftest::Rng::new(41);
+        let l: Vec<u32> = (0..2_000).map(|_| rng.range(0, 9) as u32).collect();
+        let mut c = Constraints::default();
+        c.set("--max-repeat", "3").unwrap();
+        c.set("--max-span", "200").unwrap();
+        for gap in ["1", "3"] {
+            c.set("--min-gap", gap).unwrap();
+            let now = std::time::Instant::now();
+            let picked = c.select(&l, 30).expect("must select");
+            assert!(now.elapsed().as_secs() < 5, "took {:?}", now.elapsed());
+            assert!(picked[29] - picked[0] <= 200);
+            assert!(picked.windows(2).all(|w| w[1] - w[0] >= c.min_gap));
+            let digits: Vec<u32> = picked.iter().map(|&i| l[i]).collect();
+            assert!((0..10).all(|d| digits.iter().filter(|&&x| x == d).count() <= 3));
+            assert_eq!(vec![9, 9, 9, 8, 8, 8], digits[..6]);
+        }
+    }
+
+    #[test]
+    fn test_select_matches_brute_force() {
+        Harness::new().cases(500).check(
+            "constraints",
+            |rng| {
+                let bank: Vec<u32> = (0..rng.below(12)).map(|_| rng.range(0, 9) as u32).collect();
+                let excluded: Vec<u32> =
+                    (0..rng.below(3)).map(|_| rng.range(0, 9) as u32).collect();
+                let gap = rng.range(1, 3) as usize;
+                let span = rng.range_i64(-1, 8);
+                let repeat = rng.range_i64(-1, 3);
+                (
+                    (bank, rng.range(0, 5) as usize),
+                    (excluded, gap, (span, repeat)),
+                )
+            },
+            |((bank, count), c)| brute_force(&constraints(c), bank, *count),
+            |((bank, count), c)| {
+                constraints(c)
+                    .select(bank, *count)
+                    .map(|p| p.iter().map(|&i| bank[i]).collect())
+            },
+        );
+    }
+
+    fn constraints((excluded, gap, (span, repeat)): &(Vec<u32>, usize, (i64, i64))) -> Constraints {
+        let mut c = Constraints {
+            min_gap: (*gap).max(1),
+            max_span: (*span >= 0).then_some(*span as usize),
+            max_repeat: (*repeat >= 0).then_some(*repeat as usize),
+            ..Constraints::default()
+        };
+        for &d in excluded {
+            c.excluded[d.min(9) as usize] = true;
+        }
+        c
+    }
+}
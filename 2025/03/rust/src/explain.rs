@@ -0,0 +1,70 @@
+use crate::constraints::Constraints;
+use crate::{Joltage, joltage};
+use anyhow::{Context, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Plain,
+    Ansi,
+}
+
+const BOLD_GREEN: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+
+impl Style {
+    // Bank with the picked batteries marked, e.g. "[9][8]7654321111111".
+    pub fn highlight(&self, bank: &str, picked: &[usize]) -> String {
+        let mut picked = picked.iter().peekable();
+        let mut out = String::with_capacity(bank.len() * 2);
+        for (i, ch) in bank.chars().enumerate() {
+            if picked.next_if_eq(&&i).is_none() {
+                out.push(ch);
+                continue;
+            }
+            match self {
+                Style::Plain => out += &format!("[{}]", ch),
+                Style::Ansi => out += &format!("{}{}{}", BOLD_GREEN, ch, RESET),
+            }
+        }
+        out
+    }
+}
+
+// One line per bank: the highlighted bank followed by its joltage.
+pub fn explain_str(
+    content: &str,
+    count: usize,
+    constraints: &Constraints,
+    style: Style,
+) -> Result<String> {
+    let mut out = String::new();
+    for (n, bank) in content.lines().enumerate() {
+        match joltage(bank, count, constraints).with_context(|| format!("line {}", n + 1))? {
+            Some(Joltage { value, picked }) => {
+                out += &format!("{} {}\n", style.highlight(bank, &picked), value)
+            }
+            None => out += &format!("{} infeasible\n", bank),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain() {
+        let content = "987654321111111\n818181911112111\n9\n";
+        let none = Constraints::default();
+        let plain = explain_str(content, 2, &none, Style::Plain).expect("must explain");
+        assert_eq!(
+            "[9][8]7654321111111 98\n818181[9]1111[2]111 92\n9 infeasible\n",
+            plain
+        );
+        let plain = Style::Plain.highlight("811111111111119", &[0, 14]);
+        assert_eq!("[8]1111111111111[9]", plain);
+        let ansi = Style::Ansi.highlight("4321", &[1, 3]);
+        assert_eq!("4\x1b[1;32m3\x1b[0m2\x1b[1;32m1\x1b[0m", ansi);
+    }
+}
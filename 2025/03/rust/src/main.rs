@@ -1,37 +1,55 @@
 use anyhow::{Context, Result, anyhow};
+use num_bigint::BigUint;
 use std::env;
 use std::fs::File;
-use std::io::Read;
+use std::io::{IsTerminal, Read};
 
+mod constraints;
+mod explain;
+
+use constraints::Constraints;
+
+#[cfg(test)]
 use std::cell::RefCell;
+#[cfg(test)]
 use std::collections::HashMap;
+#[cfg(test)]
 use std::rc::Rc;
 
-fn solve_file<F>(path: &str, f: F) -> Result<i64>
-where
-    F: Fn(&str) -> Result<i64>,
-{
+fn solve_file(path: &str, count: usize, constraints: &Constraints) -> Result<BigUint> {
     let mut content = String::new();
     File::open(path)?.read_to_string(&mut content)?;
-    solve_str(&content, f)
+    solve_str(&content, count, constraints)
 }
 
-fn solve_str<F>(content: &str, joltage: F) -> Result<i64>
-where
-    F: Fn(&str) -> Result<i64>,
-{
-    content.lines().map(|line| joltage(line)).sum()
-}
-
-fn part1(bank: &str) -> Result<i64> {
-    joltage(bank, 2)
+fn solve_str(content: &str, count: usize, constraints: &Constraints) -> Result<BigUint> {
+    let mut total = BigUint::ZERO;
+    let mut infeasible = Vec::new();
+    for (n, bank) in content.lines().enumerate() {
+        match joltage(bank, count, constraints).with_context(|| format!("line {}", n + 1))? {
+            Some(j) => total += j.value,
+            None => infeasible.push((n + 1).to_string()),
+        }
+    }
+    if !infeasible.is_empty() {
+        anyhow::bail!(
+            "no valid selection of {} batteries in banks on lines {}",
+            count,
+            infeasible.join(", ")
+        );
+    }
+    Ok(total)
 }
 
-fn part2(bank: &str) -> Result<i64> {
-    joltage(bank, 12)
+// Largest joltage of a bank and the indices of the batteries that make it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joltage {
+    pub value: BigUint,
+    pub picked: Vec<usize>,
 }
 
-fn joltage(bank: &str, count: usize) -> Result<i64> {
+// None when no selection of `count` batteries satisfies the constraints.
+fn joltage(bank: &str, count: usize, constraints: &Constraints) -> Result<Option<Joltage>> {
     let l: Vec<u32> = bank
         .chars()
         .map(|ch| match ch.to_digit(10) {
@@ -40,19 +58,43 @@ fn joltage(bank: &str, count: usize) -> Result<i64> {
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let mem = Rc::new(RefCell::new(HashMap::<String, i64>::new()));
-    let max = max_of(mem, l.as_slice(), count).unwrap();
-    println!("s={} joltage={}", bank, max);
-    Ok(max)
+    let Some(picked) = constraints.select(&l, count) else {
+        return Ok(None);
+    };
+    let digits: Vec<u8> = picked.iter().map(|&i| l[i] as u8).collect();
+    let value = BigUint::from_radix_be(&digits, 10).context("invalid digits")?;
+    Ok(Some(Joltage { value, picked }))
+}
+
+// Indices of the largest `count`-digit subsequence of `l`. Keeps a stack of
+// picked digits and drops a smaller one whenever a larger digit follows and
+// enough digits remain, which is O(n).
+fn select(l: &[u32], count: usize) -> Option<Vec<usize>> {
+    if count == 0 || l.len() < count {
+        return None;
+    }
+    let mut drop = l.len() - count;
+    let mut stack: Vec<usize> = Vec::with_capacity(l.len());
+    for (i, &digit) in l.iter().enumerate() {
+        while drop > 0 && stack.last().is_some_and(|&top| l[top] < digit) {
+            stack.pop();
+            drop -= 1;
+        }
+        stack.push(i);
+    }
+    stack.truncate(count);
+    Some(stack)
 }
 
+// Original memoised recursion, kept as the oracle for `select`.
+#[cfg(test)]
 fn max_of(mem: Rc<RefCell<HashMap<String, i64>>>, l: &[u32], count: usize) -> Option<i64> {
     if count == 0 || l.len() < count {
         return None;
     }
 
     if count == 1 {
-        return l.iter().max().and_then(|x| Some(*x as i64));
+        return l.iter().max().map(|x| *x as i64);
     }
 
     let key = l.iter().map(|n| n.to_string()).collect::<String>() + "" + &count.to_string();
@@ -81,21 +123,51 @@ fn max_of(mem: Rc<RefCell<HashMap<String, i64>>>, l: &[u32], count: usize) -> Op
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     let path = args.get(1).context("missing file path argument")?;
-    match args.get(2).map(|s| s.as_str()).context("missing part")? {
-        "part1" => {
-            println!("solution: {:#?}", solve_file(path, part1)?)
-        }
-        "part2" => {
-            println!("solution: {:#?}", solve_file(path, part2)?)
-        }
+    let mut count = match args.get(2).map(|s| s.as_str()).context("missing part")? {
+        "part1" => 2,
+        "part2" => 12,
         s => anyhow::bail!("unexpected part: {}", s),
     };
+    let mut explain = false;
+    let mut constraints = Constraints::default();
+    let mut flags = args[3..].iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--explain" => explain = true,
+            "--count" => match flags.next().context("missing value for --count")?.parse() {
+                Ok(0) => anyhow::bail!("--count must be positive"),
+                Ok(n) => count = n,
+                Err(e) => return Err(e).context("invalid --count"),
+            },
+            s => {
+                let value = flags
+                    .next()
+                    .with_context(|| format!("missing value for {}", s))?;
+                constraints.set(s, value)?
+            }
+        }
+    }
+    if explain {
+        let style = match std::io::stdout().is_terminal() {
+            true => explain::Style::Ansi,
+            false => explain::Style::Plain,
+        };
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        print!(
+            "{}",
+            explain::explain_str(&content, count, &constraints, style)?
+        );
+        return Ok(());
+    }
+    println!("solution: {}", solve_file(path, count, &constraints)?);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use difftest::{Harness, Rng};
     use indoc::indoc;
     const INPUT: &str = indoc! {
         "
@@ -108,13 +180,82 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let actual = solve_str(INPUT, part1).expect("must solve");
-        assert_eq!(357, actual);
+        let actual = solve_str(INPUT, 2, &Constraints::default()).expect("must solve");
+        assert_eq!(BigUint::from(357u32), actual);
     }
 
     #[test]
     fn test_part2() {
-        let actual = solve_str(INPUT, part2).expect("must solve");
-        assert_eq!(3121910778619, actual);
+        let actual = solve_str(INPUT, 12, &Constraints::default()).expect("must solve");
+        assert_eq!(BigUint::from(3121910778619u64), actual);
+    }
+
+    #[test]
+    fn test_long_selection() {
+        let bank = "1".repeat(10) + &"9".repeat(30) + "8";
+        let none = Constraints::default();
+        let actual = joltage(&bank, 31, &none)
+            .expect("must parse")
+            .expect("must select");
+        assert_eq!((10..41).collect::<Vec<_>>(), actual.picked);
+        assert_eq!("9".repeat(30) + "8", actual.value.to_string());
+
+        // 2 * (10^40 - 1) does not fit in any primitive integer
+        let content = format!("{}\n{}\n", "9".repeat(40), "9".repeat(45));
+        let total = solve_str(&content, 40, &none).expect("must solve");
+        assert_eq!(format!("1{}8", "9".repeat(39)), total.to_string());
+        assert_eq!(None, joltage("123", 4, &none).expect("must parse"));
+        let err = solve_str("123\n1234\n12\n", 3, &none).unwrap_err();
+        assert_eq!(
+            "no valid selection of 3 batteries in banks on lines 3",
+            err.to_string()
+        );
+    }
+
+    fn greedy(l: &[u32], count: usize) -> Option<i64> {
+        select(l, count).map(|picked| picked.iter().fold(0, |acc, &i| acc * 10 + l[i] as i64))
+    }
+
+    fn memoised(l: &[u32], count: usize) -> Option<i64> {
+        max_of(Rc::new(RefCell::new(HashMap::new())), l, count)
+    }
+
+    #[test]
+    fn test_select_matches_recursion() {
+        Harness::new().check(
+            "select",
+            |rng| {
+                let bank: Vec<u32> = (0..rng.below(40)).map(|_| rng.range(0, 9) as u32).collect();
+                (bank, rng.range(1, 12) as usize)
+            },
+            |(bank, count)| memoised(bank, *count),
+            |(bank, count)| greedy(bank, *count),
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --release --nocapture"]
+    fn bench_select() {
+        let mut rng = Rng::new(3);
+        let mut bank = |len: usize| (0..len).map(|_| rng.range(1, 9) as u32).collect();
+        let timed = |f: fn(&[u32], usize) -> Option<i64>, banks: &[Vec<u32>]| {
+            let now = std::time::Instant::now();
+            let sum: i64 = banks.iter().map(|b| f(b, 12).unwrap()).sum();
+            (sum, now.elapsed())
+        };
+        // the recursion is roughly cubic in the bank length
+        for len in [50, 100, 200] {
+            let banks: Vec<Vec<u32>> = (0..20).map(|_| bank(len)).collect();
+            let (expected, old) = timed(memoised, &banks);
+            let (actual, new) = timed(greedy, &banks);
+            assert_eq!(expected, actual);
+            println!("len={:>7} memoised={:>12?} greedy={:>12?}", len, old, new);
+        }
+        let banks: Vec<Vec<u32>> = (0..20).map(|_| bank(1_000_000)).collect();
+        println!(
+            "len={:>7} greedy={:>12?}",
+            1_000_000,
+            timed(greedy, &banks).1
+        );
     }
 }
@@ -0,0 +1,285 @@
+use crate::rule::{Comparison, Edge, Rule};
+
+// Occupancy of a grid, one bit per cell, every row padded to whole words.
+#[derive(Debug, Clone)]
+pub struct Bits {
+    rows: usize,
+    cols: usize,
+    words: usize,
+    data: Vec<u64>,
+}
+
+impl Bits {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let words = cols.div_ceil(64);
+        Bits {
+            rows,
+            cols,
+            words,
+            data: vec![0; rows * words],
+        }
+    }
+
+    fn at(&self, i: usize) -> (usize, u64) {
+        let (y, x) = (i / self.cols, i % self.cols);
+        (y * self.words + x / 64, 1 << (x % 64))
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        let (w, bit) = self.at(i);
+        self.data[w] & bit != 0
+    }
+
+    pub fn set(&mut self, i: usize) {
+        let (w, bit) = self.at(i);
+        self.data[w] |= bit;
+    }
+
+    pub fn clear(&mut self, i: usize) {
+        let (w, bit) = self.at(i);
+        self.data[w] &= !bit;
+    }
+
+    // Cells x..x + 64 of row y, bit i holding column x + i. Cells off the
+    // grid are empty, full or wrapped around depending on `edge`.
+    fn word(&self, y: isize, x: isize, edge: Edge) -> u64 {
+        let y = match (0..self.rows as isize).contains(&y) {
+            true => y as usize,
+            false => match edge {
+                Edge::Clip => return 0,
+                Edge::Filled => return !0,
+                Edge::Torus => y.rem_euclid(self.rows as isize) as usize,
+            },
+        };
+        let row = &self.data[y * self.words..(y + 1) * self.words];
+        if x >= 0 && x as usize + 64 <= self.cols {
+            let (w, b) = (x as usize / 64, x as usize % 64);
+            return match b {
+                0 => row[w],
+                _ => (row[w] >> b) | (row[w + 1] << (64 - b)),
+            };
+        }
+        // only the words at either end of a row get here
+        let mut out = 0;
+        for i in 0..64 {
+            let x = x + i;
+            let bit = match (0..self.cols as isize).contains(&x) {
+                true => row[x as usize / 64] >> (x as usize % 64) & 1,
+                false => match edge {
+                    Edge::Clip => 0,
+                    Edge::Filled => 1,
+                    Edge::Torus => {
+                        let x = x.rem_euclid(self.cols as isize) as usize;
+                        row[x / 64] >> (x % 64) & 1
+                    }
+                },
+            };
+            out |= bit << i;
+        }
+        out
+    }
+
+    // Row y shifted so that bit i of word w is column w * 64 + i + dx.
+    fn shifted(&self, y: isize, dx: isize, edge: Edge, out: &mut [u64]) {
+        if !(0..self.rows as isize).contains(&y) && edge != Edge::Torus {
+            out.fill(self.word(y, 0, edge));
+            return;
+        }
+        let row = y.rem_euclid(self.rows as isize) as usize;
+        let row = &self.data[row * self.words..(row + 1) * self.words];
+        // words w in fast.. lie inside the row, read straight off it
+        let fast = ((-dx).max(0) as usize).div_ceil(64);
+        let end = ((self.cols as isize - dx - 64).div_euclid(64) + 1)
+            .clamp(fast as isize, self.words as isize) as usize;
+        let (q, b) = (dx.div_euclid(64), dx.rem_euclid(64) as u32);
+        let src = &row[(fast as isize + q) as usize..];
+        match b {
+            0 => out[fast..end].copy_from_slice(&src[..end - fast]),
+            _ => {
+                for (out, pair) in out[fast..end].iter_mut().zip(src.windows(2)) {
+                    *out = (pair[0] >> b) | (pair[1] << (64 - b));
+                }
+            }
+        }
+        for w in (0..fast).chain(end..self.words) {
+            out[w] = self.word(y, w as isize * 64 + dx, edge);
+        }
+    }
+
+    // Rolls of row y that `rule` removes. Neighbour counts are kept in
+    // bit-sliced counters, P words per grid word: plane j holds bit j of
+    // every cell's count, and adding a shifted row is a ripple-carry.
+    fn removable_row<const P: usize>(
+        &self,
+        y: usize,
+        offsets: &[(isize, isize)],
+        rule: &Rule,
+        shifted: &mut [u64],
+        out: &mut [u64],
+    ) {
+        let mut count = vec![[0u64; P]; self.words];
+        for &(dy, dx) in offsets {
+            self.shifted(y as isize + dy, dx, rule.edge, shifted);
+            for (&word, count) in shifted.iter().zip(count.iter_mut()) {
+                let mut carry = word;
+                for plane in count.iter_mut() {
+                    (*plane, carry) = (*plane ^ carry, *plane & carry);
+                }
+            }
+        }
+        let row = &self.data[y * self.words..(y + 1) * self.words];
+        for ((out, count), &rolls) in out.iter_mut().zip(&count).zip(row) {
+            let (lt, eq) = compare(count, rule.threshold);
+            let hit = match rule.comparison {
+                Comparison::Less => lt,
+                Comparison::LessEq => lt | eq,
+                Comparison::Eq => eq,
+                Comparison::GreaterEq => !lt,
+                Comparison::Greater => !(lt | eq),
+            };
+            *out = rolls & hit;
+        }
+    }
+
+    // Picks enough planes to count every offset, from a few fixed widths.
+    fn removable_rows(
+        &self,
+        rows: impl Iterator<Item = usize>,
+        offsets: &[(isize, isize)],
+        rule: &Rule,
+        out: &mut Vec<usize>,
+    ) {
+        // rows without columns have nothing to remove
+        if self.words == 0 {
+            return;
+        }
+        let mut shifted = vec![0; self.words];
+        let mut mask = vec![0; self.words];
+        let planes = usize::BITS - offsets.len().leading_zeros();
+        for y in rows {
+            match planes {
+                0..=3 => self.removable_row::<3>(y, offsets, rule, &mut shifted, &mut mask),
+                4 => self.removable_row::<4>(y, offsets, rule, &mut shifted, &mut mask),
+                5..=8 => self.removable_row::<8>(y, offsets, rule, &mut shifted, &mut mask),
+                _ => self.removable_row::<16>(y, offsets, rule, &mut shifted, &mut mask),
+            }
+            self.cells(y, &mask, out);
+        }
+    }
+
+    // Cell indices of the rolls in row y set in `mask`.
+    fn cells(&self, y: usize, mask: &[u64], out: &mut Vec<usize>) {
+        for (w, &word) in mask.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                out.push(y * self.cols + w * 64 + word.trailing_zeros() as usize);
+                word &= word - 1;
+            }
+        }
+    }
+
+    // Every roll `rule` removes right now.
+    pub fn removable(&self, offsets: &[(isize, isize)], rule: &Rule) -> Vec<usize> {
+        let mut out = Vec::new();
+        self.removable_rows(0..self.rows, offsets, rule, &mut out);
+        out
+    }
+
+    // Simultaneous rounds of removal, returning the cells removed in each.
+    // Only rows within reach of the last round's removals can change, so
+    // later rounds skip the rest of the grid.
+    pub fn peel(&mut self, offsets: &[(isize, isize)], rule: &Rule) -> Vec<Vec<usize>> {
+        let mut reach: Vec<isize> = offsets.iter().map(|&(dy, _)| dy).collect();
+        reach.sort();
+        reach.dedup();
+        let mut dirty = vec![true; self.rows];
+        let mut rounds = Vec::new();
+        loop {
+            let mut removed = Vec::new();
+            let rows = (0..self.rows).filter(|&y| dirty[y]);
+            self.removable_rows(rows, offsets, rule, &mut removed);
+            if removed.is_empty() {
+                return rounds;
+            }
+            dirty.fill(false);
+            for &i in &removed {
+                self.clear(i);
+                // the rows that see row y through some offset dy
+                let y = (i / self.cols) as isize;
+                for &dy in &reach {
+                    let seen_from = match rule.edge {
+                        Edge::Torus => (y - dy).rem_euclid(self.rows as isize),
+                        Edge::Clip | Edge::Filled => y - dy,
+                    };
+                    if (0..self.rows as isize).contains(&seen_from) {
+                        dirty[seen_from as usize] = true;
+                    }
+                }
+            }
+            rounds.push(removed);
+        }
+    }
+}
+
+// Bit-sliced comparison of every count against `threshold`, from the most
+// significant plane down: masks of the counts below it and equal to it.
+fn compare(planes: &[u64], threshold: usize) -> (u64, u64) {
+    if threshold >> planes.len() != 0 {
+        return (!0, 0);
+    }
+    let (mut lt, mut eq) = (0, !0);
+    for (j, &plane) in planes.iter().enumerate().rev() {
+        match threshold >> j & 1 {
+            1 => {
+                lt |= eq & !plane;
+                eq &= plane;
+            }
+            _ => eq &= !plane,
+        }
+    }
+    (lt, eq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        // counts 0..8 in the low 8 lanes
+        let mut planes = [0u64; 4];
+        for count in 0..8u64 {
+            for (j, plane) in planes.iter_mut().enumerate() {
+                *plane |= (count >> j & 1) << count;
+            }
+        }
+        let (lt, eq) = compare(&planes, 4);
+        assert_eq!(0b0000_1111, lt & 0xff);
+        assert_eq!(0b0001_0000, eq & 0xff);
+        assert_eq!((!0, 0), compare(&planes, 16));
+    }
+
+    #[test]
+    fn test_word_edges() {
+        // rolls at (0, 0), (0, 65), (0, 69), (1, 0) and (1, 69) of a 2x70 grid
+        let mut bits = Bits::new(2, 70);
+        for i in [0, 65, 69, 70, 139] {
+            bits.set(i);
+        }
+        assert!(bits.get(139) && !bits.get(138));
+        assert_eq!(1, bits.word(0, 0, Edge::Clip));
+        assert_eq!(1 << 5 | 1 << 9, bits.word(0, 60, Edge::Clip));
+        // column -1 is empty, full, or the last column of the same row
+        assert_eq!(0b10, bits.word(0, -1, Edge::Clip) & 0b11);
+        assert_eq!(0b11, bits.word(0, -1, Edge::Filled) & 0b11);
+        assert_eq!(0b11, bits.word(0, -1, Edge::Torus) & 0b11);
+        assert_eq!(1 | 1 << 4, bits.word(0, 65, Edge::Clip));
+        assert_eq!(1 | 1 << 4 | 1 << 5, bits.word(0, 65, Edge::Torus) & 0xff);
+        assert_eq!(!0 << 5 | 1 | 1 << 4, bits.word(0, 65, Edge::Filled));
+        // rows -1 and 2 wrap to the other row
+        assert_eq!(bits.word(1, 0, Edge::Clip), bits.word(-1, 0, Edge::Torus));
+        assert_eq!(bits.word(0, 0, Edge::Clip), bits.word(2, 0, Edge::Torus));
+        assert_eq!(0, bits.word(2, 0, Edge::Clip));
+        assert_eq!(!0, bits.word(2, 0, Edge::Filled));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::history::History;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+// Frame k shows the grid after k rounds, with removed cells marked by the
+// round that took them.
+pub struct Frames<'a> {
+    history: &'a History,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Roll,
+    Removed(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Ascii,
+    Ppm,
+    Png,
+}
+
+impl TryFrom<&str> for Format {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "ascii" => Ok(Format::Ascii),
+            "ppm" => Ok(Format::Ppm),
+            "png" => Ok(Format::Png),
+            s => anyhow::bail!("unexpected frame format: {}", s),
+        }
+    }
+}
+
+impl<'a> Frames<'a> {
+    pub fn new(history: &'a History) -> Self {
+        Frames { history }
+    }
+
+    pub fn len(&self) -> usize {
+        self.history.rounds() + 1
+    }
+
+    fn cell(&self, frame: usize, i: usize) -> Cell {
+        match (self.history.rolls[i], self.history.removed_in[i]) {
+            (false, _) => Cell::Empty,
+            (true, r) if r == 0 || r as usize > frame => Cell::Roll,
+            (true, r) => Cell::Removed(r as usize),
+        }
+    }
+
+    fn symbol(cell: Cell) -> char {
+        match cell {
+            Cell::Empty => '.',
+            Cell::Roll => '@',
+            Cell::Removed(r) if r < 36 => char::from_digit(r as u32, 36).unwrap(),
+            Cell::Removed(_) => '+',
+        }
+    }
+
+    // Early rounds are red, late ones blue; rolls stay white.
+    fn colour(&self, cell: Cell) -> [u8; 3] {
+        match cell {
+            Cell::Empty => [0, 0, 0],
+            Cell::Roll => [255, 255, 255],
+            Cell::Removed(r) => {
+                let t = (r - 1) as f64 / (self.history.rounds().max(2) - 1) as f64;
+                hue(240.0 * t)
+            }
+        }
+    }
+
+    // Removed cells show their round in base 36, '+' from round 36 on.
+    pub fn ascii(&self, frame: usize) -> String {
+        self.render(frame, |cell| Self::symbol(cell).to_string())
+    }
+
+    pub fn ansi(&self, frame: usize) -> String {
+        self.render(frame, |cell| match cell {
+            Cell::Removed(_) => {
+                let [r, g, b] = self.colour(cell);
+                format!("\x1b[38;2;{};{};{}m{}\x1b[0m", r, g, b, Self::symbol(cell))
+            }
+            _ => Self::symbol(cell).to_string(),
+        })
+    }
+
+    fn render<F: Fn(Cell) -> String>(&self, frame: usize, f: F) -> String {
+        let mut out = String::new();
+        for y in 0..self.history.rows {
+            for x in 0..self.history.cols {
+                out += &f(self.cell(frame, y * self.history.cols + x));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // RGB pixels with every cell drawn as a `scale` x `scale` square.
+    fn pixels(&self, frame: usize, scale: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.history.rows * self.history.cols * scale * scale * 3);
+        for y in 0..self.history.rows * scale {
+            for x in 0..self.history.cols * scale {
+                let i = (y / scale) * self.history.cols + x / scale;
+                out.extend(self.colour(self.cell(frame, i)));
+            }
+        }
+        out
+    }
+
+    pub fn ppm(&self, frame: usize, scale: usize) -> Vec<u8> {
+        let mut out = format!(
+            "P6\n{} {}\n255\n",
+            self.history.cols * scale,
+            self.history.rows * scale
+        )
+        .into_bytes();
+        out.extend(self.pixels(frame, scale));
+        out
+    }
+
+    // PNG has no empty images, so empty grids are an error.
+    pub fn png(&self, frame: usize, scale: usize) -> Result<Vec<u8>> {
+        let (width, height) = (self.history.cols * scale, self.history.rows * scale);
+        if width == 0 || height == 0 {
+            anyhow::bail!("can't encode a {}x{} image as PNG", width, height);
+        }
+        let pixels = self.pixels(frame, scale);
+        // every scanline starts with filter type 0
+        let mut raw = Vec::with_capacity(pixels.len() + height);
+        for line in pixels.chunks(width * 3) {
+            raw.push(0);
+            raw.extend_from_slice(line);
+        }
+        Ok(png(width as u32, height as u32, &raw))
+    }
+
+    // Writes frame-NNN.<ext> for every frame into `dir`.
+    pub fn export(&self, dir: &Path, format: Format, scale: usize) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        for frame in 0..self.len() {
+            let (ext, bytes) = match format {
+                Format::Ascii => ("txt", self.ascii(frame).into_bytes()),
+                Format::Ppm => ("ppm", self.ppm(frame, scale)),
+                Format::Png => ("png", self.png(frame, scale)?),
+            };
+            let path = dir.join(format!("frame-{:03}.{}", frame, ext));
+            fs::write(&path, bytes).with_context(|| format!("writing {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn replay(&self, delay: Duration) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        for frame in 0..self.len() {
+            writeln!(
+                stdout,
+                "\x1b[H\x1b[2J{}round {}/{}",
+                self.ansi(frame),
+                frame,
+                self.history.rounds()
+            )?;
+            stdout.flush()?;
+            if frame + 1 < self.len() {
+                thread::sleep(delay);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Fully saturated colour for a hue in degrees.
+fn hue(h: f64) -> [u8; 3] {
+    let x = 1.0 - ((h / 60.0) % 2.0 - 1.0).abs();
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    [r, g, b].map(|c| (c * 255.0).round() as u8)
+}
+
+// 8-bit RGB PNG around already filtered scanlines, deflated as stored blocks.
+fn png(width: u32, height: u32, raw: &[u8]) -> Vec<u8> {
+    let mut ihdr = Vec::new();
+    ihdr.extend(width.to_be_bytes());
+    ihdr.extend(height.to_be_bytes());
+    ihdr.extend([8, 2, 0, 0, 0]);
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend(adler32(raw).to_be_bytes());
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", &ihdr), (b"IDAT", &zlib), (b"IEND", &Vec::new())] {
+        out.extend((data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend(kind);
+        out.extend(data);
+        let crc = crc32(&out[start..]);
+        out.extend(crc.to_be_bytes());
+    }
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Grid, peel};
+
+    fn peeled(input: &str) -> History {
+        let mut grid = Grid::from_str(input);
+        let removed = peel(&mut grid);
+        History::new(&grid, &removed)
+    }
+
+    #[test]
+    fn test_ascii_frames() {
+        let history = peeled("@@@\n@@@\n@@@\n");
+        let frames = Frames::new(&history);
+        assert_eq!(4, frames.len());
+        assert_eq!("@@@\n@@@\n@@@\n", frames.ascii(0));
+        assert_eq!("1@1\n@@@\n1@1\n", frames.ascii(1));
+        assert_eq!("121\n2@2\n121\n", frames.ascii(2));
+        assert_eq!("121\n232\n121\n", frames.ascii(3));
+        assert!(frames.ansi(3).contains("\x1b[38;2;255;0;0m1\x1b[0m"));
+        assert!(frames.ansi(3).contains("\x1b[38;2;0;0;255m3\x1b[0m"));
+    }
+
+    #[test]
+    fn test_images() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+
+        let history = peeled("@.\n..\n");
+        let frames = Frames::new(&history);
+        let ppm = frames.ppm(1, 2);
+        assert!(ppm.starts_with(b"P6\n4 4\n255\n"));
+        assert_eq!(11 + 4 * 4 * 3, ppm.len());
+        assert_eq!([255, 0, 0, 255, 0, 0, 0, 0, 0], ppm[11..20]);
+
+        let png = frames.png(1, 2).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x04\0\0\0\x04"));
+        assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+        // 4 scanlines of 1 filter byte and 4 pixels in a single stored block
+        let raw = 4 * (1 + 4 * 3);
+        assert_eq!(8 + 25 + 12 + 2 + 5 + raw + 4 + 12, png.len());
+
+        for (input, size) in [("", "0x0"), ("\n\n", "0x4")] {
+            let history = peeled(input);
+            let frames = Frames::new(&history);
+            assert_eq!(11, frames.ppm(0, 2).len());
+            let err = frames.png(0, 2).unwrap_err();
+            assert_eq!(
+                format!("can't encode a {} image as PNG", size),
+                err.to_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{Grid, Position};
+
+// How many rolls `peel` removed in each round, out of how many.
+pub struct Summary {
+    pub rolls: usize,
+    pub per_round: Vec<usize>,
+}
+
+impl Summary {
+    // `after` is the grid once `peel` is done, `removed` what it took per
+    // round.
+    pub fn new(after: &Grid, removed: &[Vec<Position>]) -> Self {
+        let per_round: Vec<usize> = removed.iter().map(Vec::len).collect();
+        Summary {
+            rolls: after.rolls().count() + per_round.iter().sum::<usize>(),
+            per_round,
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.per_round.len()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.per_round.iter().sum()
+    }
+
+    // Rolls that no round removes.
+    pub fn stable(&self) -> usize {
+        self.rolls - self.removed()
+    }
+
+    pub fn report(&self) -> String {
+        let mut out = format!("rolls: {}\nrounds: {}\n", self.rolls, self.rounds());
+        for (round, removed) in self.per_round.iter().enumerate() {
+            out += &format!("round {}: removed {}\n", round + 1, removed);
+        }
+        out += &format!("removed: {}\nstable: {}\n", self.removed(), self.stable());
+        out
+    }
+}
+
+// Round in which each roll of a grid was removed by `peel`. Takes a few
+// bytes per cell, so it is only built for frames and the rounds grid.
+pub struct History {
+    pub rows: usize,
+    pub cols: usize,
+    pub rolls: Vec<bool>,
+    // round in which each cell was removed, 0 for never
+    pub removed_in: Vec<u32>,
+    rounds: usize,
+}
+
+impl History {
+    // `after` is the grid once `peel` is done, `removed` what it took per
+    // round; the rolls before peeling are the ones left plus the removed.
+    pub fn new(after: &Grid, removed: &[Vec<Position>]) -> Self {
+        let mut removed_in = vec![0; after.rows * after.cols];
+        for (round, positions) in removed.iter().enumerate() {
+            for pos in positions {
+                removed_in[after.index(pos).unwrap()] = round as u32 + 1;
+            }
+        }
+        History {
+            rows: after.rows,
+            cols: after.cols,
+            rolls: (0..after.rows * after.cols)
+                .map(|i| after.occupied(i) || removed_in[i] > 0)
+                .collect(),
+            removed_in,
+            rounds: removed.len(),
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    // One line per row of comma-separated cells: the removal round of a
+    // removed roll, 0 for a stable roll and nothing for an empty cell.
+    pub fn rounds_csv(&self) -> String {
+        let mut out = String::new();
+        for y in 0..self.rows {
+            let row: Vec<String> = (y * self.cols..(y + 1) * self.cols)
+                .map(|i| match self.rolls[i] {
+                    true => self.removed_in[i].to_string(),
+                    false => String::new(),
+                })
+                .collect();
+            out += &row.join(",");
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peel;
+
+    fn peeled(input: &str) -> (Summary, History) {
+        let mut grid = Grid::from_str(input);
+        let removed = peel(&mut grid);
+        (Summary::new(&grid, &removed), History::new(&grid, &removed))
+    }
+
+    #[test]
+    fn test_history() {
+        let (summary, history) = peeled("@@@@\n@@@@\n@@@@\n@@@@\n");
+        let report = "rolls: 16\nrounds: 1\nround 1: removed 4\nremoved: 4\nstable: 12\n";
+        assert_eq!(report, summary.report());
+        assert_eq!("1,0,0,1\n0,0,0,0\n0,0,0,0\n1,0,0,1\n", history.rounds_csv());
+
+        let (summary, history) = peeled("@@@.\n@@@.\n@@@.\n");
+        assert_eq!((3, 0), (summary.rounds(), summary.stable()));
+        assert_eq!(3, history.rounds());
+        assert_eq!("1,2,1,\n2,3,2,\n1,2,1,\n", history.rounds_csv());
+    }
+}
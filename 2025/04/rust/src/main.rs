@@ -2,22 +2,28 @@ use anyhow::{Context, Result};
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
 
-fn solve_file<F>(path: &str, f: F) -> Result<i64>
-where
-    F: Fn(&mut Grid) -> i64,
-{
+mod bits;
+mod frames;
+mod history;
+mod rule;
+
+use rule::{Edge, Mode, Rule};
+
+fn load(path: &str, rule: Rule, backend: Backend) -> Result<Grid> {
     let mut content = String::new();
     File::open(path)?.read_to_string(&mut content)?;
-    Ok(solve_str(&content, f))
+    Ok(Grid::with_backend(&content, rule, backend))
 }
 
-fn solve_str<F>(content: &str, could_be_accessed: F) -> i64
+#[cfg(test)]
+fn solve_str<F, T>(content: &str, could_be_accessed: F) -> T
 where
-    F: Fn(&mut Grid) -> i64,
+    F: Fn(&mut Grid) -> T,
 {
     let mut grid = Grid::from_str(content);
-    println!("{:?}", grid);
     could_be_accessed(&mut grid)
 }
 
@@ -27,164 +33,365 @@ pub struct Position {
     y: usize,
 }
 
-#[derive(Debug)]
+// How a grid keeps its cells: a neighbour count per cell, updated on every
+// removal, or one bit per cell with counts worked out a word at a time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Counts,
+    Bits,
+}
+
+impl TryFrom<&str> for Backend {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "counts" => Ok(Backend::Counts),
+            "bits" => Ok(Backend::Bits),
+            s => anyhow::bail!("unexpected backend: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Cells {
+    // 1 + number of neighbouring rolls for a roll, 0 for an empty cell
+    Counts(Vec<u16>),
+    Bits(bits::Bits),
+}
+
+#[derive(Debug, Clone)]
 pub struct Grid {
     rows: usize,
     cols: usize,
-    data: Vec<u8>,
+    cells: Cells,
+    offsets: Vec<(isize, isize)>,
+    rule: Rule,
 }
 
 impl Grid {
-    // Relative neighbor offsets (8 directions)
-    const NEIGHBORS: &[(isize, isize)] = &[
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-
+    #[cfg(test)]
     fn from_str(s: &str) -> Self {
-        let mut cols: usize = 0;
-        let mut rows: usize = 0;
-        let data: Vec<u8> = s
+        Self::new(s, Rule::default())
+    }
+
+    #[cfg(test)]
+    fn new(s: &str, rule: Rule) -> Self {
+        Self::with_backend(s, rule, Backend::Counts)
+    }
+
+    fn with_backend(s: &str, rule: Rule, backend: Backend) -> Self {
+        let rows = s.lines().count();
+        let cols = s.lines().next().map_or(0, |line| line.len());
+        let rolls = s
             .lines()
-            .map(|line| {
-                if cols == 0 {
-                    cols = line.len();
+            .flat_map(|line| line.chars().chain(std::iter::repeat('.')).take(cols))
+            .enumerate()
+            .filter(|&(_, ch)| ch == '@')
+            .map(|(i, _)| i);
+        let cells = match backend {
+            Backend::Counts => {
+                let mut data = vec![0; rows * cols];
+                rolls.for_each(|i| data[i] = 1);
+                Cells::Counts(data)
+            }
+            Backend::Bits => {
+                let mut bits = bits::Bits::new(rows, cols);
+                rolls.for_each(|i| bits.set(i));
+                Cells::Bits(bits)
+            }
+        };
+        let offsets = rule.neighbourhood.offsets();
+        let mut grid = Self {
+            rows,
+            cols,
+            cells,
+            offsets,
+            rule,
+        };
+        if let Cells::Counts(_) = grid.cells {
+            for pos in grid.rolls().collect::<Vec<_>>() {
+                let i = grid.index(&pos).unwrap();
+                let neighbours = grid.neighbor_rolls(&pos).count() + grid.filled_outside(&pos);
+                if let Cells::Counts(data) = &mut grid.cells {
+                    data[i] += neighbours as u16;
                 }
-                rows += 1;
-                line.chars()
-            })
-            .flatten()
-            .map(|ch| match ch {
-                '@' => 1,
-                _ => 0,
-            })
-            .collect::<Vec<_>>();
-        let mut grid = Self { rows, cols, data };
-        for pos in grid.rolls().collect::<Vec<_>>() {
-            let i = grid.index(&pos).unwrap();
-            grid.data[i] += grid.neighbor_rolls(&pos).count() as u8;
+            }
         }
 
         grid
     }
 
+    fn occupied(&self, i: usize) -> bool {
+        match &self.cells {
+            Cells::Counts(data) => data[i] > 0,
+            Cells::Bits(bits) => bits.get(i),
+        }
+    }
+
+    // Cell `(dy, dx)` away from `pos`, wrapping around on a torus; None when
+    // it lies outside the grid.
+    fn step(&self, pos: &Position, (dy, dx): (isize, isize)) -> Option<Position> {
+        if self.rule.edge == Edge::Torus {
+            return Some(Position {
+                y: (pos.y as isize + dy).rem_euclid(self.rows as isize) as usize,
+                x: (pos.x as isize + dx).rem_euclid(self.cols as isize) as usize,
+            });
+        }
+        // Use checked_add_signed to stay in usize without casts back and forth
+        let y = pos.y.checked_add_signed(dy).filter(|&y| y < self.rows)?;
+        let x = pos.x.checked_add_signed(dx).filter(|&x| x < self.cols)?;
+        Some(Position { x, y })
+    }
+
+    // Neighbouring rolls inside the grid. On a torus smaller than the
+    // neighbourhood one cell can show up for several offsets, and is counted
+    // once per offset.
     fn neighbor_rolls<'a>(&'a self, pos: &'a Position) -> impl Iterator<Item = Position> + use<'a> {
-        Self::NEIGHBORS.iter().filter_map(|(dy, dx)| {
-            // Use checked_add_signed to stay in usize without casts back and forth
-            let ny = match pos.y.checked_add_signed(*dy) {
-                Some(v) if v < self.rows => v,
-                _ => return None,
-            };
-            let nx = match pos.x.checked_add_signed(*dx) {
-                Some(v) if v < self.cols => v,
-                _ => return None,
-            };
-            let np = Position { x: nx, y: ny };
-            if self.data[self.index(&np).unwrap()] > 0 {
-                return Some(np);
-            }
-            None
-        })
+        self.offsets
+            .iter()
+            .filter_map(|&offset| self.step(pos, offset))
+            .filter(|np| self.occupied(self.index(np).unwrap()))
+    }
+
+    // Neighbours outside the grid that count as rolls which never go away.
+    fn filled_outside(&self, pos: &Position) -> usize {
+        match self.rule.edge {
+            Edge::Filled => self
+                .offsets
+                .iter()
+                .filter(|&&offset| self.step(pos, offset).is_none())
+                .count(),
+            Edge::Clip | Edge::Torus => 0,
+        }
     }
 
     fn rolls<'a>(&'a self) -> impl Iterator<Item = Position> + use<'a> {
-        self.data
-            .iter()
-            .enumerate()
-            .filter(|(_, x)| **x > 0)
-            .map(|(i, _)| Position {
+        (0..self.rows * self.cols)
+            .filter(|&i| self.occupied(i))
+            .map(|i| Position {
                 y: i / self.cols,
                 x: i % self.cols,
             })
     }
 
     fn index(&self, pos: &Position) -> Option<usize> {
-        if pos.x > self.cols {
+        if pos.x >= self.cols {
             return None;
         }
-        if pos.y > self.rows {
+        if pos.y >= self.rows {
             return None;
         }
         Some(pos.y * self.cols + pos.x)
     }
 
-    fn get_neighbors(&self, p: &Position) -> Option<u8> {
-        Some(self.data[self.index(p)?].max(1) - 1)
+    fn get_neighbors(&self, p: &Position) -> Option<usize> {
+        let i = self.index(p)?;
+        match &self.cells {
+            Cells::Counts(data) => Some(data[i].max(1) as usize - 1),
+            Cells::Bits(_) if !self.occupied(i) => Some(0),
+            Cells::Bits(_) => Some(self.neighbor_rolls(p).count() + self.filled_outside(p)),
+        }
     }
 
-    fn del(&mut self, pos: &Position) {
-        if let Some(i) = self.index(pos) {
-            if let Some(elem) = self.data.get_mut(i) {
-                *elem = 0;
-            }
-            self.neighbor_rolls(pos)
-                .collect::<Vec<_>>()
-                .iter()
-                .all(|np| {
-                    let ni = self.index(np).unwrap();
-                    if let Some(elem) = self.data.get_mut(ni) {
-                        *elem -= 1
+    fn removable(&self, p: &Position) -> bool {
+        self.index(p).is_some_and(|i| {
+            self.occupied(i) && self.rule.removable(self.get_neighbors(p).unwrap())
+        })
+    }
+
+    // Removes the roll at `pos` and queues every neighbour that becomes
+    // removable because of it.
+    fn del(&mut self, pos: &Position, queue: &mut Vec<Position>) {
+        let Some(i) = self.index(pos) else {
+            return;
+        };
+        if let Cells::Bits(_) = self.cells {
+            // counts follow the bits, so replay the decrements the counts
+            // backend makes, in the same order, on counts taken beforehand
+            let mut counts: Vec<(usize, usize)> = Vec::new();
+            let neighbours: Vec<Position> = self
+                .neighbor_rolls(pos)
+                .filter(|np| self.index(np) != Some(i))
+                .collect();
+            for np in neighbours {
+                let ni = self.index(&np).unwrap();
+                let c = match counts.iter_mut().find(|(j, _)| *j == ni) {
+                    Some((_, c)) => c,
+                    None => {
+                        counts.push((ni, self.get_neighbors(&np).unwrap()));
+                        &mut counts.last_mut().unwrap().1
                     }
-                    true
-                });
+                };
+                let was = self.rule.removable(*c);
+                *c -= 1;
+                if !was && self.rule.removable(*c) {
+                    queue.push(np);
+                }
+            }
+            if let Cells::Bits(bits) = &mut self.cells {
+                bits.clear(i);
+            }
+            return;
+        }
+        if let Cells::Counts(data) = &mut self.cells {
+            data[i] = 0;
+        }
+        for np in self.neighbor_rolls(pos).collect::<Vec<_>>() {
+            let was = self.removable(&np);
+            let ni = self.index(&np).unwrap();
+            if let Cells::Counts(data) = &mut self.cells {
+                data[ni] -= 1;
+            }
+            if !was && self.removable(&np) {
+                queue.push(np);
+            }
+        }
+    }
+
+    fn position(&self, i: usize) -> Position {
+        Position {
+            y: i / self.cols,
+            x: i % self.cols,
         }
     }
 }
 
 fn part1(grid: &mut Grid) -> i64 {
-    grid.rolls()
-        .filter(|pos| grid.get_neighbors(pos).is_some_and(|x| x < 4))
-        .inspect(|x| {
-            println!(
-                "found: pos={:?} rolls={}",
-                x,
-                grid.get_neighbors(x).unwrap()
-            )
-        })
-        .count() as i64
+    if let Cells::Bits(bits) = &grid.cells {
+        return bits.removable(&grid.offsets, &grid.rule).len() as i64;
+    }
+    grid.rolls().filter(|pos| grid.removable(pos)).count() as i64
 }
 
-fn part2(grid: &mut Grid) -> i64 {
-    let mut count: i64 = 0;
-    loop {
-        let to_remove: Vec<Position> = grid
-            .rolls()
-            .filter(|pos| grid.get_neighbors(pos).is_some_and(|x| x < 4))
-            .inspect(|x| {
-                println!(
-                    "found: pos={:?} rolls={}",
-                    x,
-                    grid.get_neighbors(x).unwrap()
-                )
-            })
+// Removes removable rolls round by round and returns the rolls removed in
+// each round. Neighbour counts only drop, so a roll turns removable at most
+// once and only when a neighbour goes; it is queued then and checked again
+// when its round comes, since rules like "=2" can stop holding.
+fn peel(grid: &mut Grid) -> Vec<Vec<Position>> {
+    if let (Cells::Bits(bits), Mode::Simultaneous) = (&mut grid.cells, grid.rule.mode) {
+        let rounds = bits.peel(&grid.offsets, &grid.rule);
+        return rounds
+            .into_iter()
+            .map(|round| round.into_iter().map(|i| grid.position(i)).collect())
             .collect();
-
-        if to_remove.len() == 0 {
-            return count;
+    }
+    let mut queue: Vec<Position> = grid.rolls().filter(|pos| grid.removable(pos)).collect();
+    let mut rounds = Vec::new();
+    while !queue.is_empty() {
+        let mut next = Vec::new();
+        let removed = match grid.rule.mode {
+            Mode::Simultaneous => {
+                queue.retain(|pos| grid.removable(pos));
+                for pos in &queue {
+                    grid.del(pos, &mut next);
+                }
+                queue
+            }
+            Mode::Sequential => {
+                let mut removed = Vec::new();
+                for pos in queue {
+                    if grid.removable(&pos) {
+                        grid.del(&pos, &mut next);
+                        removed.push(pos);
+                    }
+                }
+                removed
+            }
+        };
+        if !removed.is_empty() {
+            rounds.push(removed);
         }
+        queue = next;
+    }
+    rounds
+}
+
+struct Options {
+    rule: Rule,
+    backend: Backend,
+    frames: Option<PathBuf>,
+    format: frames::Format,
+    scale: usize,
+    replay: Option<Duration>,
+    rounds_grid: Option<PathBuf>,
+}
 
-        count += to_remove.iter().fold(0i64, |acc, pos| {
-            grid.del(pos);
-            acc + 1
-        });
+fn parse_options(flags: &[String]) -> Result<Options> {
+    let mut options = Options {
+        rule: Rule::default(),
+        backend: Backend::Counts,
+        frames: None,
+        format: frames::Format::Ascii,
+        scale: 4,
+        replay: None,
+        rounds_grid: None,
+    };
+    let mut delay = Duration::from_millis(200);
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let mut value = || {
+            flags
+                .next()
+                .with_context(|| format!("missing value for {}", flag))
+        };
+        match flag.as_str() {
+            "--neighbourhood" => {
+                options.rule.neighbourhood = rule::Neighbourhood::try_from(value()?.as_str())?
+            }
+            "--removable" => options.rule.set_removable(value()?)?,
+            "--mode" => options.rule.mode = Mode::try_from(value()?.as_str())?,
+            "--backend" => options.backend = Backend::try_from(value()?.as_str())?,
+            "--edge" => options.rule.edge = Edge::try_from(value()?.as_str())?,
+            "--frames" => options.frames = Some(PathBuf::from(value()?)),
+            "--format" => options.format = frames::Format::try_from(value()?.as_str())?,
+            "--scale" => match value()?.parse().context("invalid --scale")? {
+                0 => anyhow::bail!("--scale must be positive"),
+                n => options.scale = n,
+            },
+            "--rounds-grid" => options.rounds_grid = Some(PathBuf::from(value()?)),
+            "--replay" => options.replay = Some(delay),
+            "--delay" => {
+                delay = Duration::from_millis(value()?.parse().context("invalid --delay")?)
+            }
+            s => anyhow::bail!("unexpected flag: {}", s),
+        }
     }
+    options.replay = options.replay.map(|_| delay);
+    Ok(options)
 }
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     let path = args.get(1).context("missing file path argument")?;
-    match args.get(2).map(|s| s.as_str()).context("missing part")? {
+    let part = args.get(2).map(|s| s.as_str()).context("missing part")?;
+    let options = parse_options(&args[3..])?;
+    let mut grid = load(path, options.rule, options.backend)?;
+    match part {
         "part1" => {
-            println!("solution: {:#?}", solve_file(path, part1)?)
+            println!("solution: {:#?}", part1(&mut grid))
         }
         "part2" => {
-            println!("solution: {:#?}", solve_file(path, part2)?)
+            let rounds = peel(&mut grid);
+            // per-cell history is only needed for frames and the rounds grid
+            let frames = options.frames.is_some() || options.replay.is_some();
+            if frames || options.rounds_grid.is_some() {
+                let history = history::History::new(&grid, &rounds);
+                let frames = frames::Frames::new(&history);
+                if let Some(dir) = &options.frames {
+                    frames.export(dir, options.format, options.scale)?;
+                }
+                if let Some(delay) = options.replay {
+                    frames.replay(delay)?;
+                }
+                if let Some(path) = &options.rounds_grid {
+                    std::fs::write(path, history.rounds_csv())
+                        .with_context(|| format!("writing {}", path.display()))?;
+                }
+            }
+            let summary = history::Summary::new(&grid, &rounds);
+            print!("{}", summary.report());
+            println!("solution: {:#?}", summary.removed())
         }
         s => anyhow::bail!("unexpected part: {}", s),
     };
@@ -194,6 +401,7 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use difftest::Harness;
     use indoc::indoc;
     const INPUT: &str = indoc! {
         "
@@ -217,7 +425,313 @@ mod tests {
     }
     #[test]
     fn test_part2() {
-        let actual = solve_str(INPUT, part2);
+        let actual: usize = solve_str(INPUT, peel).iter().map(Vec::len).sum();
         assert_eq!(43, actual);
     }
+
+    #[test]
+    fn test_peel_rounds() {
+        let actual: Vec<usize> = solve_str(INPUT, peel).iter().map(Vec::len).collect();
+        assert_eq!(vec![13, 12, 7, 5, 2, 1, 1, 1, 1], actual);
+    }
+
+    // The original round loop: rescan every roll until nothing is removable.
+    fn rescan(grid: &mut Grid) -> Vec<usize> {
+        let mut rounds = Vec::new();
+        loop {
+            let to_remove: Vec<Position> = grid.rolls().filter(|pos| grid.removable(pos)).collect();
+            if to_remove.is_empty() {
+                return rounds;
+            }
+            for pos in &to_remove {
+                grid.del(pos, &mut Vec::new());
+            }
+            rounds.push(to_remove.len());
+        }
+    }
+
+    impl Grid {
+        fn rolls_after_peel(mut self) -> Vec<(usize, usize)> {
+            peel(&mut self);
+            self.rolls().map(|p| (p.y, p.x)).collect()
+        }
+    }
+
+    // Zero columns stand for a blank first line, which truncates the rows
+    // after it to nothing.
+    fn render((cols, cells): &(usize, Vec<bool>)) -> String {
+        let symbol = |&roll: &bool| if roll { '@' } else { '.' };
+        if *cols == 0 {
+            return format!("\n{}\n", cells.iter().map(symbol).collect::<String>());
+        }
+        let cols = *cols;
+        let mut out = String::new();
+        for row in cells.chunks(cols).filter(|row| row.len() == cols) {
+            out.extend(row.iter().map(symbol));
+            out.push('\n');
+        }
+        out
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut rule = Rule::default();
+        rule.set_removable("<3").unwrap();
+        let mut removable = |neighbourhood: &str| {
+            rule.neighbourhood = rule::Neighbourhood::try_from(neighbourhood).unwrap();
+            part1(&mut Grid::new("@@\n@@\n", rule))
+        };
+        assert_eq!(0, removable("moore"));
+        assert_eq!(4, removable("von-neumann"));
+        assert_eq!(2, removable("hex"));
+
+        // removing either roll of a pair leaves the other with no neighbour
+        let mut rule = Rule::default();
+        rule.set_removable("=1").unwrap();
+        assert_eq!(1, peel(&mut Grid::new("@@\n", rule)).len());
+        assert_eq!(2, peel(&mut Grid::new("@@\n", rule))[0].len());
+        rule.mode = Mode::Sequential;
+        assert_eq!(
+            vec![1],
+            peel(&mut Grid::new("@@\n", rule))
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_index_bounds() {
+        let grid = Grid::from_str("@@@\n@@@\n");
+        assert_eq!(Some(5), grid.index(&Position { x: 2, y: 1 }));
+        assert_eq!(None, grid.index(&Position { x: 3, y: 0 }));
+        assert_eq!(None, grid.index(&Position { x: 0, y: 2 }));
+    }
+
+    // Neighbour counts of every cell, row by row.
+    fn counts(input: &str, neighbourhood: &str, edge: Edge) -> Vec<usize> {
+        let rule = Rule {
+            neighbourhood: rule::Neighbourhood::try_from(neighbourhood).unwrap(),
+            edge,
+            ..Rule::default()
+        };
+        let grid = Grid::new(input, rule);
+        (0..grid.rows * grid.cols)
+            .map(|i| {
+                grid.get_neighbors(&Position {
+                    x: i % grid.cols,
+                    y: i / grid.cols,
+                })
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edges_full_grid() {
+        // corners, edges and the centre of a full 3x3 grid
+        let full = "@@@\n@@@\n@@@\n";
+        assert_eq!(
+            vec![3, 5, 3, 5, 8, 5, 3, 5, 3],
+            counts(full, "moore", Edge::Clip)
+        );
+        assert_eq!(vec![8; 9], counts(full, "moore", Edge::Torus));
+        assert_eq!(vec![8; 9], counts(full, "moore", Edge::Filled));
+        assert_eq!(
+            vec![2, 3, 2, 3, 4, 3, 2, 3, 2],
+            counts(full, "von-neumann", Edge::Clip)
+        );
+        assert_eq!(vec![4; 9], counts(full, "von-neumann", Edge::Torus));
+        assert_eq!(vec![4; 9], counts(full, "von-neumann", Edge::Filled));
+    }
+
+    #[test]
+    fn test_edges_wrap_to_the_opposite_side() {
+        // a roll in every corner and in the middle of every edge of a 5x5 grid,
+        // with nothing next to each other inside the grid
+        let input = "@.@.@\n.....\n@...@\n.....\n@.@.@\n";
+        let roll = |counts: &[usize], y: usize, x: usize| counts[y * 5 + x];
+        for (y, x) in [
+            (0, 0),
+            (0, 2),
+            (0, 4),
+            (2, 0),
+            (2, 4),
+            (4, 0),
+            (4, 2),
+            (4, 4),
+        ] {
+            assert_eq!(
+                0,
+                roll(&counts(input, "moore", Edge::Clip), y, x),
+                "({}, {})",
+                y,
+                x
+            );
+        }
+        let torus = counts(input, "moore", Edge::Torus);
+        // (0, 0) wraps onto (4, 4), (0, 4), (4, 0); (0, 2) onto (4, 2) only
+        assert_eq!(3, roll(&torus, 0, 0));
+        assert_eq!(3, roll(&torus, 4, 4));
+        assert_eq!(1, roll(&torus, 0, 2));
+        assert_eq!(1, roll(&torus, 2, 4));
+        assert_eq!(2, roll(&counts(input, "von-neumann", Edge::Torus), 4, 0));
+        let filled = counts(input, "moore", Edge::Filled);
+        assert_eq!(5, roll(&filled, 0, 0));
+        assert_eq!(3, roll(&filled, 0, 2));
+        assert_eq!(3, roll(&filled, 2, 0));
+        assert_eq!(5, roll(&filled, 4, 4));
+    }
+
+    #[test]
+    fn test_edges_on_tiny_grids() {
+        // on a 1x1 torus every offset lands on the roll itself
+        assert_eq!(vec![8], counts("@\n", "moore", Edge::Torus));
+        assert_eq!(vec![0], counts("@\n", "moore", Edge::Clip));
+        assert_eq!(vec![8], counts("@\n", "moore", Edge::Filled));
+        assert_eq!(vec![1, 1], counts("@@\n", "von-neumann", Edge::Clip));
+        // filled outside cells are never removed
+        let rule = Rule {
+            edge: Edge::Filled,
+            ..Rule::default()
+        };
+        assert!(peel(&mut Grid::new("@@@\n@@@\n@@@\n", rule)).is_empty());
+        let rule = Rule {
+            edge: Edge::Torus,
+            ..Rule::default()
+        };
+        // each roll of a 2x2 torus sees the other through all four diagonals
+        assert!(peel(&mut Grid::new("@.\n.@\n", rule)).is_empty());
+    }
+
+    fn random_rule((neighbourhood, comparison, threshold): &(usize, usize, u64)) -> Rule {
+        use rule::{Comparison, Neighbourhood};
+        let neighbourhoods = [
+            Neighbourhood::Moore,
+            Neighbourhood::VonNeumann,
+            Neighbourhood::Hex,
+            Neighbourhood::Radius(2),
+        ];
+        let comparisons = [
+            Comparison::Less,
+            Comparison::LessEq,
+            Comparison::Eq,
+            Comparison::GreaterEq,
+            Comparison::Greater,
+        ];
+        Rule {
+            neighbourhood: neighbourhoods[*neighbourhood % neighbourhoods.len()],
+            comparison: comparisons[*comparison % comparisons.len()],
+            threshold: *threshold as usize,
+            ..Rule::default()
+        }
+    }
+
+    #[test]
+    fn test_peel_matches_rescan() {
+        Harness::new().cases(500).check(
+            "peel",
+            |rng| {
+                let cols = rng.range(1, 12) as usize;
+                let cells = (0..cols * rng.range(1, 12) as usize)
+                    .map(|_| rng.below(4) > 0)
+                    .collect();
+                let rule = (rng.below(4), rng.below(5), rng.range(0, 9));
+                ((cols, cells), rule)
+            },
+            |(grid, rule)| rescan(&mut Grid::new(&render(grid), random_rule(rule))),
+            |(grid, rule)| {
+                let rounds = peel(&mut Grid::new(&render(grid), random_rule(rule)));
+                rounds.iter().map(Vec::len).collect::<Vec<_>>()
+            },
+        );
+    }
+
+    #[test]
+    fn test_sequential_removes_the_same_rolls_for_monotone_rules() {
+        Harness::new().cases(200).check(
+            "sequential",
+            |rng| {
+                let cols = rng.range(1, 12) as usize;
+                let cells = (0..cols * rng.range(1, 12) as usize)
+                    .map(|_| rng.below(4) > 0)
+                    .collect();
+                ((cols, cells), (rng.below(4), rng.below(2), rng.range(0, 9)))
+            },
+            |(grid, rule)| Grid::new(&render(grid), random_rule(rule)).rolls_after_peel(),
+            |(grid, rule)| {
+                let rule = Rule {
+                    mode: Mode::Sequential,
+                    ..random_rule(rule)
+                };
+                Grid::new(&render(grid), rule).rolls_after_peel()
+            },
+        );
+    }
+
+    type Case = ((usize, Vec<bool>), ((usize, usize, u64), (usize, usize)));
+
+    // part1, per-round counts and the rolls left, with any edge and mode
+    fn run_backend(case: &Case, backend: Backend) -> (i64, Vec<usize>, Vec<(usize, usize)>) {
+        let (grid, (rule, (edge, mode))) = case;
+        let rule = Rule {
+            edge: [Edge::Clip, Edge::Torus, Edge::Filled][*edge],
+            mode: [Mode::Simultaneous, Mode::Sequential][*mode],
+            ..random_rule(rule)
+        };
+        let mut grid = Grid::with_backend(&render(grid), rule, backend);
+        let first = part1(&mut grid.clone());
+        let rounds = peel(&mut grid).iter().map(Vec::len).collect();
+        (first, rounds, grid.rolls().map(|p| (p.y, p.x)).collect())
+    }
+
+    #[test]
+    fn test_bits_match_counts() {
+        Harness::new().cases(500).check(
+            "bits",
+            |rng| {
+                // including empty grids and ones without columns
+                let cols = rng.range(0, 200) as usize;
+                let cells = (0..cols.max(1) * rng.range(0, 8) as usize)
+                    .map(|_| rng.below(4) > 0)
+                    .collect();
+                let rule = (rng.below(4), rng.below(5), rng.range(0, 9));
+                ((cols, cells), (rule, (rng.below(3), rng.below(2))))
+            },
+            |case: &Case| run_backend(case, Backend::Counts),
+            |case: &Case| run_backend(case, Backend::Bits),
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --release --nocapture"]
+    fn bench_backends() {
+        let mut rng = difftest::Rng::new(4);
+        let (rows, cols) = (2_000, 40_000);
+        let mut input = String::with_capacity(rows * (cols + 1));
+        for _ in 0..rows {
+            input.extend((0..cols).map(|_| if rng.below(4) > 0 { '@' } else { '.' }));
+            input.push('\n');
+        }
+        let mut removed = Vec::new();
+        for backend in [Backend::Counts, Backend::Bits] {
+            let now = std::time::Instant::now();
+            let mut grid = Grid::with_backend(&input, Rule::default(), backend);
+            println!("parsed in {:?}", now.elapsed());
+            let bytes = match &grid.cells {
+                Cells::Counts(data) => data.len() * 2,
+                Cells::Bits(_) => rows * cols.div_ceil(64) * 8,
+            };
+            let rounds = peel(&mut grid);
+            removed.push(rounds.iter().map(Vec::len).collect::<Vec<_>>());
+            println!(
+                "{:?}: {} rounds in {:?}, {} MiB of cells",
+                backend,
+                rounds.len(),
+                now.elapsed(),
+                bytes >> 20
+            );
+        }
+        assert_eq!(removed[0], removed[1]);
+    }
 }
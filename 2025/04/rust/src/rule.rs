@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+
+// Cells counted as neighbours. Hex grids use axial coordinates: row y is
+// shifted half a cell right of row y - 1, so the six neighbours are the Moore
+// ones without the (-1, -1) and (1, 1) diagonals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Neighbourhood {
+    VonNeumann,
+    Moore,
+    Radius(usize),
+    Hex,
+}
+
+pub const MAX_RADIUS: usize = 100;
+
+impl TryFrom<&str> for Neighbourhood {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "von-neumann" => Ok(Neighbourhood::VonNeumann),
+            "moore" => Ok(Neighbourhood::Moore),
+            "hex" => Ok(Neighbourhood::Hex),
+            s => match s.strip_prefix("radius=") {
+                Some(r) => match r
+                    .parse()
+                    .with_context(|| format!("invalid radius {:?}", r))?
+                {
+                    r @ 1..=MAX_RADIUS => Ok(Neighbourhood::Radius(r)),
+                    r => anyhow::bail!("radius {} must be between 1 and {}", r, MAX_RADIUS),
+                },
+                None => anyhow::bail!("unexpected neighbourhood: {}", s),
+            },
+        }
+    }
+}
+
+impl Neighbourhood {
+    // Relative (dy, dx) offsets of every neighbour.
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let r = match self {
+            Neighbourhood::Radius(r) => *r as isize,
+            _ => 1,
+        };
+        let mut out = Vec::new();
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let keep = match self {
+                    Neighbourhood::VonNeumann => dy.abs() + dx.abs() == 1,
+                    Neighbourhood::Hex => dy != dx,
+                    Neighbourhood::Moore | Neighbourhood::Radius(_) => (dy, dx) != (0, 0),
+                };
+                if keep {
+                    out.push((dy, dx));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessEq,
+    Eq,
+    GreaterEq,
+    Greater,
+}
+
+// Whether a batch of removable rolls goes at once, decided on the grid as it
+// was at the start of the round, or one by one, re-checking each roll against
+// the removals before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Simultaneous,
+    Sequential,
+}
+
+impl TryFrom<&str> for Mode {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "simultaneous" => Ok(Mode::Simultaneous),
+            "sequential" => Ok(Mode::Sequential),
+            s => anyhow::bail!("unexpected mode: {}", s),
+        }
+    }
+}
+
+// What lies beyond the grid: nothing, the opposite side, or rolls that are
+// never removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Clip,
+    Torus,
+    Filled,
+}
+
+impl TryFrom<&str> for Edge {
+    type Error = anyhow::Error;
+
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "clip" => Ok(Edge::Clip),
+            "torus" => Ok(Edge::Torus),
+            "filled" => Ok(Edge::Filled),
+            s => anyhow::bail!("unexpected edge: {}", s),
+        }
+    }
+}
+
+// A roll is removable when its neighbour count compares to `threshold`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    pub neighbourhood: Neighbourhood,
+    pub comparison: Comparison,
+    pub threshold: usize,
+    pub mode: Mode,
+    pub edge: Edge,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule {
+            neighbourhood: Neighbourhood::Moore,
+            comparison: Comparison::Less,
+            threshold: 4,
+            mode: Mode::Simultaneous,
+            edge: Edge::Clip,
+        }
+    }
+}
+
+impl Rule {
+    // Sets comparison and threshold from "<4", "<=3", "=2", ">=5" or ">5".
+    pub fn set_removable(&mut self, s: &str) -> Result<()> {
+        let (comparison, n) = [
+            ("<=", Comparison::LessEq),
+            (">=", Comparison::GreaterEq),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Eq),
+        ]
+        .into_iter()
+        .find_map(|(prefix, c)| s.strip_prefix(prefix).map(|n| (c, n)))
+        .with_context(|| format!("removable must start with <, <=, =, >= or >: {:?}", s))?;
+        self.comparison = comparison;
+        self.threshold = n
+            .parse()
+            .with_context(|| format!("invalid threshold {:?}", n))?;
+        Ok(())
+    }
+
+    pub fn removable(&self, neighbours: usize) -> bool {
+        match self.comparison {
+            Comparison::Less => neighbours < self.threshold,
+            Comparison::LessEq => neighbours <= self.threshold,
+            Comparison::Eq => neighbours == self.threshold,
+            Comparison::GreaterEq => neighbours >= self.threshold,
+            Comparison::Greater => neighbours > self.threshold,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule() {
+        assert_eq!(8, Neighbourhood::Moore.offsets().len());
+        assert_eq!(4, Neighbourhood::VonNeumann.offsets().len());
+        assert_eq!(
+            24,
+            Neighbourhood::try_from("radius=2").unwrap().offsets().len()
+        );
+        let hex = Neighbourhood::try_from("hex").unwrap().offsets();
+        assert_eq!(
+            vec![(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)],
+            hex
+        );
+        assert!(Neighbourhood::try_from("radius=0").is_err());
+        assert!(Neighbourhood::try_from("square").is_err());
+
+        let mut rule = Rule::default();
+        assert!(rule.removable(3) && !rule.removable(4));
+        rule.set_removable(">=5").unwrap();
+        assert_eq!(
+            (Comparison::GreaterEq, 5),
+            (rule.comparison, rule.threshold)
+        );
+        assert!(rule.removable(5) && !rule.removable(4));
+        rule.set_removable("=2").unwrap();
+        assert!(rule.removable(2) && !rule.removable(1) && !rule.removable(3));
+        assert!(rule.set_removable("4").is_err());
+        assert!(rule.set_removable("<x").is_err());
+        assert_eq!(Mode::Sequential, Mode::try_from("sequential").unwrap());
+        assert_eq!(Edge::Torus, Edge::try_from("torus").unwrap());
+        assert!(Edge::try_from("wrap").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{Range, compact};
+
+// Set operations on sorted, disjoint and non-touching range lists, as
+// produced by `compact`. Results keep the same shape.
+
+pub fn union(a: &[Range], b: &[Range]) -> Vec<Range> {
+    compact(a.iter().chain(b).cloned().collect())
+}
+
+pub fn intersection(a: &[Range], b: &[Range]) -> Vec<Range> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let beg = a[i].beg.max(b[j].beg);
+        let end = a[i].end.min(b[j].end);
+        if beg <= end {
+            result.push(Range { beg, end });
+        }
+        // The range ending first cannot overlap anything further.
+        if a[i].end < b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+pub fn difference(a: &[Range], b: &[Range]) -> Vec<Range> {
+    let all = Range {
+        beg: 0,
+        end: usize::MAX,
+    };
+    intersection(a, &complement(b, &all))
+}
+
+// The IDs within `bounds` that no range in `a` covers.
+pub fn complement(a: &[Range], bounds: &Range) -> Vec<Range> {
+    let mut result = Vec::new();
+    let mut next = Some(bounds.beg);
+    for r in a {
+        let Some(beg) = next else { break };
+        if r.end < beg {
+            continue;
+        }
+        if r.beg > bounds.end {
+            break;
+        }
+        if r.beg > beg {
+            result.push(Range {
+                beg,
+                end: r.beg - 1,
+            });
+        }
+        next = r.end.checked_add(1);
+    }
+    if let Some(beg) = next
+        && beg <= bounds.end
+    {
+        result.push(Range {
+            beg,
+            end: bounds.end,
+        });
+    }
+    result
+}
+
+// One range per line, either as "beg-end" or with `Range`'s grouped
+// `Display`.
+pub fn render(ranges: &[Range], pretty: bool) -> String {
+    let mut out = String::new();
+    for r in ranges {
+        match pretty {
+            true => out += &format!("{}\n", r),
+            false => out += &format!("{}-{}\n", r.beg, r.end),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use difftest::Harness;
+    use std::collections::BTreeSet;
+
+    type Ranges = Vec<(u64, u64)>;
+
+    fn ranges(pairs: &[(usize, usize)]) -> Vec<Range> {
+        let ranges = pairs.iter().map(|&(beg, end)| Range { beg, end });
+        compact(ranges.collect())
+    }
+
+    fn pairs(ranges: &[Range]) -> Vec<(usize, usize)> {
+        ranges.iter().map(|r| (r.beg, r.end)).collect()
+    }
+
+    // Ranges given as (beg, len) so that shrinking keeps them valid.
+    fn sets((a, b): &(Ranges, Ranges)) -> (Vec<Range>, Vec<Range>) {
+        let convert = |v: &Ranges| {
+            let v: Vec<_> = v.iter().map(|&(beg, len)| (beg, beg + len)).collect();
+            let v: Vec<_> = v.iter().map(|&(b, e)| (b as usize, e as usize)).collect();
+            ranges(&v)
+        };
+        (convert(a), convert(b))
+    }
+
+    // Every ID up to `MAX`, which the generated ranges never pass.
+    const MAX: usize = 80;
+
+    fn members(ranges: &[Range]) -> BTreeSet<usize> {
+        (0..=MAX)
+            .filter(|&id| ranges.iter().any(|r| r.contains(id)))
+            .collect()
+    }
+
+    fn intervals(set: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+        let ranges = set.iter().map(|&id| Range { beg: id, end: id });
+        pairs(&compact(ranges.collect()))
+    }
+
+    #[test]
+    fn test_operations() {
+        let a = ranges(&[(3, 5), (10, 14), (16, 20), (12, 18)]);
+        let b = ranges(&[(1, 4), (9, 9), (15, 30)]);
+        assert_eq!(vec![(1, 5), (9, 30)], pairs(&union(&a, &b)));
+        assert_eq!(vec![(3, 4), (15, 20)], pairs(&intersection(&a, &b)));
+        assert_eq!(vec![(5, 5), (10, 14)], pairs(&difference(&a, &b)));
+        assert_eq!(vec![(1, 2), (9, 9), (21, 30)], pairs(&difference(&b, &a)));
+        let bounds = Range { beg: 0, end: 25 };
+        assert_eq!(
+            vec![(0, 2), (6, 9), (21, 25)],
+            pairs(&complement(&a, &bounds))
+        );
+        let bounds = Range { beg: 4, end: 12 };
+        assert_eq!(vec![(6, 9)], pairs(&complement(&a, &bounds)));
+        assert_eq!(vec![(4, 12)], pairs(&complement(&[], &bounds)));
+    }
+
+    #[test]
+    fn test_full_range() {
+        let all = ranges(&[(0, usize::MAX)]);
+        let bounds = Range {
+            beg: 0,
+            end: usize::MAX,
+        };
+        assert!(complement(&all, &bounds).is_empty());
+        assert_eq!(pairs(&all), pairs(&complement(&[], &bounds)));
+        let tail = ranges(&[(5, usize::MAX)]);
+        assert_eq!(vec![(0, 4)], pairs(&difference(&all, &tail)));
+        assert_eq!(vec![(0, 4)], pairs(&complement(&tail, &bounds)));
+    }
+
+    #[test]
+    fn test_render() {
+        let a = ranges(&[(3, 5), (1_000, 25_000_000)]);
+        assert_eq!("3-5\n1000-25000000\n", render(&a, false));
+        assert_eq!("[3, 5]\n[1_000, 25_000_000]\n", render(&a, true));
+    }
+
+    #[test]
+    fn test_operations_match_sets() {
+        let generate = |rng: &mut difftest::Rng| {
+            let mut side = || {
+                (0..rng.below(6))
+                    .map(|_| (rng.range(0, 60), rng.range(0, 10)))
+                    .collect()
+            };
+            (side(), side())
+        };
+        let bounds = Range { beg: 20, end: 50 };
+        Harness::new().check(
+            "algebra",
+            generate,
+            |input| {
+                let (a, b) = sets(input);
+                let (a, b) = (members(&a), members(&b));
+                let window: BTreeSet<usize> = (20..=50).collect();
+                [
+                    intervals(&a.union(&b).copied().collect()),
+                    intervals(&a.intersection(&b).copied().collect()),
+                    intervals(&a.difference(&b).copied().collect()),
+                    intervals(&window.difference(&a).copied().collect()),
+                ]
+            },
+            |input| {
+                let (a, b) = sets(input);
+                [
+                    pairs(&union(&a, &b)),
+                    pairs(&intersection(&a, &b)),
+                    pairs(&difference(&a, &b)),
+                    pairs(&complement(&a, &bounds)),
+                ]
+            },
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::Range;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+// Fresh ID ranges and available ingredient IDs, with the input line each one
+// came from.
+#[derive(Debug, Default)]
+pub struct Input {
+    pub ranges: Vec<Range>,
+    pub range_lines: Vec<usize>,
+    pub ids: Vec<usize>,
+}
+
+// Ranges come first, then IDs, separated by one or more blank lines. Lines
+// are trimmed, so CRLF endings and stray whitespace do not matter, and blank
+// lines around the sections are skipped. Leading blank lines followed by a
+// line without a range separator mean the range section is empty.
+pub fn parse(content: &str) -> Result<Input> {
+    let mut input = Input::default();
+    let (mut in_ids, mut leading_blank) = (false, false);
+    let mut seen: HashMap<usize, usize> = HashMap::new();
+    for (n, line) in content.lines().enumerate() {
+        let (n, line) = (n + 1, line.trim());
+        if line.is_empty() {
+            in_ids |= !input.ranges.is_empty();
+            leading_blank |= input.ranges.is_empty();
+            continue;
+        }
+        let range_shaped = line.get(1..).is_some_and(|rest| rest.contains('-'));
+        in_ids |= input.ranges.is_empty() && leading_blank && !range_shaped;
+        if !in_ids {
+            let range = Range::try_from(line).with_context(|| format!("line {}", n))?;
+            input.ranges.push(range);
+            input.range_lines.push(n);
+            continue;
+        }
+        let id = bound(line).with_context(|| format!("line {}: invalid id", n))?;
+        if let Some(first) = seen.insert(id, n) {
+            anyhow::bail!("line {}: duplicate id {} (first on line {})", n, id, first);
+        }
+        input.ids.push(id);
+    }
+    Ok(input)
+}
+
+// A non-negative integer, with a clearer error for negative ones.
+pub fn bound(s: &str) -> Result<usize> {
+    if let Some(n) = s.strip_prefix('-')
+        && n.parse::<u128>().is_ok()
+    {
+        anyhow::bail!("{} is negative", s);
+    }
+    s.parse()
+        .with_context(|| format!("{:?} is not a number", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(content: &str) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+        let input = parse(content).expect("must parse");
+        let ranges = input.ranges.iter().map(|r| (r.beg, r.end)).collect();
+        (ranges, input.range_lines, input.ids)
+    }
+
+    fn err(content: &str) -> String {
+        format!("{:#}", parse(content).unwrap_err())
+    }
+
+    #[test]
+    fn test_sections() {
+        let expected = (vec![(3, 5), (10, 14)], vec![1, 2], vec![1, 5]);
+        assert_eq!(expected, ok("3-5\n10-14\n\n1\n5\n"));
+        assert_eq!(expected, ok("3-5\r\n10-14\r\n\r\n1\r\n5\r\n"));
+        assert_eq!(expected, ok("3-5  \n 10-14\n \t\n1 \n5"));
+        let (ranges, lines, ids) = ok("\n\n3-5\n10-14\n\n\n\n1\n\n5\n\n");
+        assert_eq!((expected.0, vec![3, 4], expected.2), (ranges, lines, ids));
+        assert_eq!((vec![(3, 5)], vec![1], vec![]), ok("3-5\n"));
+        assert_eq!((vec![], vec![], vec![]), ok(""));
+        assert_eq!((vec![], vec![], vec![]), ok("\n\n"));
+        assert_eq!((vec![], vec![], vec![1, 5]), ok("\n1\n5\n"));
+        assert_eq!((vec![], vec![], vec![1, 5]), ok("\r\n\r\n1\r\n\r\n5\r\n"));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            "line 2: reversed range 20-10: start must not exceed end",
+            err("3-5\n20-10\n")
+        );
+        assert_eq!("line 1: -3 is negative", err("-3-5\n"));
+        assert_eq!("line 1: -5 is negative", err("3--5\n"));
+        assert_eq!("line 1: not enough items for range \"7\"", err("7\n\n1\n"));
+        assert_eq!("line 4: invalid id: -1 is negative", err("3-5\n\n1\n-1\n"));
+        assert_eq!(
+            "line 4: invalid id: \"1-2\" is not a number: invalid digit found in string",
+            err("3-5\n\n1\n1-2\n")
+        );
+        assert_eq!(
+            "line 5: duplicate id 1 (first on line 3)",
+            err("3-5\n\n1\n2\n1\n")
+        );
+    }
+}
@@ -1,22 +1,26 @@
-use anyhow::{Context, Error, Result, anyhow};
+use anyhow::{Context, Error, Result};
 use num_format::{CustomFormat, Grouping, ToFormattedString};
 use std::env;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::Read;
 
+mod algebra;
+mod input;
+mod provenance;
+
 fn solve_file<F>(path: &str, f: F) -> Result<usize>
 where
-    F: Fn(&str) -> usize,
+    F: Fn(&str) -> Result<usize>,
 {
     let mut content = String::new();
     File::open(path)?.read_to_string(&mut content)?;
-    Ok(solve_str(&content, f))
+    solve_str(&content, f)
 }
 
-fn solve_str<F>(content: &str, f: F) -> usize
+fn solve_str<F>(content: &str, f: F) -> Result<usize>
 where
-    F: Fn(&str) -> usize,
+    F: Fn(&str) -> Result<usize>,
 {
     f(content)
 }
@@ -60,46 +64,46 @@ impl Range {
 impl TryFrom<&str> for Range {
     type Error = Error;
 
+    // "beg-end"; the separator is the first '-' after the start, so that
+    // negative bounds get reported as such.
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        let parts = s.split("-").collect::<Vec<_>>();
-        let beg: usize = parts
-            .get(0)
-            .ok_or(anyhow!("{}: not enough items for range", s))?
-            .parse()?;
-        let end: usize = parts
-            .get(1)
-            .ok_or(anyhow!("{}: not enough items for range", s))?
-            .parse()?;
+        let sep = s
+            .char_indices()
+            .skip(1)
+            .find(|&(_, ch)| ch == '-')
+            .map(|(i, _)| i)
+            .with_context(|| format!("not enough items for range {:?}", s))?;
+        let beg = input::bound(&s[..sep])?;
+        let end = input::bound(&s[sep + 1..])?;
+        if beg > end {
+            anyhow::bail!("reversed range {}: start must not exceed end", s);
+        }
         Ok(Self { beg, end })
     }
 }
 
-fn part1(input: &str) -> usize {
-    let ranges: Vec<Range> = input
-        .lines()
-        .take_while(|line| line.len() > 0)
-        .map(|s| s.try_into())
-        .collect::<Result<Vec<_>, _>>()
-        .expect("failed to parse ranges");
-    let ingridients: Vec<usize> = input
-        .lines()
-        .skip(ranges.len() + 1)
-        .map(|s| s.parse())
-        .collect::<Result<Vec<_>, _>>()
-        .expect("failed to parse ingridients");
-
-    ingridients
-        .iter()
-        .flat_map(|i| ranges.iter().map(|r| r.contains(*i)).find(|&x| x))
-        .filter(|&x| x)
-        .count()
+fn part1(content: &str) -> Result<usize> {
+    let input = input::parse(content)?;
+    let ranges = compact(input.ranges);
+    Ok(input.ids.iter().filter(|&&i| fresh(&ranges, i)).count())
+}
+
+// Whether `id` lies in one of the sorted, disjoint `ranges`: the only
+// candidate is the last range starting at or before it.
+fn fresh(ranges: &[Range], id: usize) -> bool {
+    match ranges.partition_point(|r| r.beg <= id) {
+        0 => false,
+        n => ranges[n - 1].contains(id),
+    }
 }
 
 fn compact(mut ranges: Vec<Range>) -> Vec<Range> {
     ranges.sort_by_key(|r| r.beg);
 
     let mut result = Vec::with_capacity(ranges.len());
-    let mut current = ranges[0].to_owned();
+    let Some(mut current) = ranges.first().cloned() else {
+        return result;
+    };
 
     for r in ranges.into_iter().skip(1) {
         if r.beg <= current.end.saturating_add(1) {
@@ -116,16 +120,76 @@ fn compact(mut ranges: Vec<Range>) -> Vec<Range> {
     result
 }
 
-fn part2(input: &str) -> usize {
-    let ranges: Vec<Range> = compact(
-        input
-            .lines()
-            .take_while(|line| line.len() > 0)
-            .map(|s| s.try_into())
-            .collect::<Result<Vec<Range>, _>>()
-            .expect("must collect ranges"),
-    );
-    ranges.iter().map(|r| r.items()).sum()
+fn part2(content: &str) -> Result<usize> {
+    let ranges = compact(input::parse(content)?.ranges);
+    Ok(ranges.iter().map(|r| r.items()).sum())
+}
+
+// Explains every ID given, or every ingredient when none are, by the input
+// lines of the ranges that contain it.
+fn query(input: &str, ids: &[String]) -> Result<String> {
+    let input = input::parse(input)?;
+    let ids = match ids.is_empty() {
+        true => input.ids,
+        false => ids
+            .iter()
+            .map(|id| input::bound(id).context("invalid id"))
+            .collect::<Result<Vec<usize>>>()?,
+    };
+    let index = provenance::Index::new(&input.ranges, &input.range_lines);
+    let mut out = String::new();
+    let mut spoiled = 0;
+    for &id in &ids {
+        spoiled += index.matches(id).is_empty() as usize;
+        out += &index.explain(id);
+        out.push('\n');
+    }
+    out += &format!("fresh: {}, spoiled: {}\n", ids.len() - spoiled, spoiled);
+    Ok(out)
+}
+
+fn read(path: &str) -> Result<String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut content))
+        .with_context(|| format!("reading {}", path))?;
+    Ok(content)
+}
+
+// Applies a set operation to the ranges of the file at `path` and either a
+// second range file or, for the complement, the bounds "lo-hi". Any ID
+// section in the files is ignored.
+fn ranges_op(path: &str, op: &str, args: &[String]) -> Result<String> {
+    let (args, pretty) = match args.iter().position(|a| a == "--pretty") {
+        Some(i) => ([&args[..i], &args[i + 1..]].concat(), true),
+        None => (args.to_vec(), false),
+    };
+    let [arg] = args.as_slice() else {
+        anyhow::bail!(
+            "usage: solution <path> {} <{}> [--pretty]",
+            op,
+            match op {
+                "complement" => "lo-hi",
+                _ => "path",
+            }
+        );
+    };
+    let load = |path: &str| -> Result<Vec<Range>> {
+        let input = input::parse(&read(path)?).with_context(|| format!("parsing {}", path))?;
+        Ok(compact(input.ranges))
+    };
+    let a = load(path)?;
+    let result = match op {
+        "complement" => {
+            let bounds = Range::try_from(arg.as_str()).context("invalid bounds")?;
+            algebra::complement(&a, &bounds)
+        }
+        "union" => algebra::union(&a, &load(arg)?),
+        "intersection" => algebra::intersection(&a, &load(arg)?),
+        "difference" => algebra::difference(&a, &load(arg)?),
+        s => anyhow::bail!("unexpected operation: {}", s),
+    };
+    Ok(algebra::render(&result, pretty))
 }
 
 fn main() -> Result<()> {
@@ -138,6 +202,10 @@ fn main() -> Result<()> {
         "part2" => {
             println!("solution: {:#?}", solve_file(path, part2)?)
         }
+        "query" => print!("{}", query(&read(path)?, &args[3..])?),
+        op @ ("union" | "intersection" | "difference" | "complement") => {
+            print!("{}", ranges_op(path, op, &args[3..])?)
+        }
         s => anyhow::bail!("unexpected part: {}", s),
     };
     Ok(())
@@ -146,6 +214,7 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use difftest::{Harness, Rng};
     use indoc::indoc;
     const INPUT: &str = indoc! {
         "
@@ -165,13 +234,130 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let actual = solve_str(INPUT, part1);
+        let actual = solve_str(INPUT, part1).expect("must solve");
         assert_eq!(3, actual);
     }
 
     #[test]
     fn test_part2() {
-        let actual = solve_str(INPUT, part2);
+        let actual = solve_str(INPUT, part2).expect("must solve");
         assert_eq!(14, actual);
     }
+
+    #[test]
+    fn test_empty_ranges() {
+        for input in ["", "\n", "\n\n1\n2\n", "\n1\n5\n"] {
+            assert_eq!(0, solve_str(input, part1).expect("must solve"));
+            assert_eq!(0, solve_str(input, part2).expect("must solve"));
+        }
+        assert!(compact(vec![]).is_empty());
+    }
+
+    // The original lookup: every ID against every range.
+    fn scan(content: &str) -> Result<usize> {
+        let input = input::parse(content)?;
+        let ids = input.ids.iter();
+        Ok(ids
+            .filter(|&&i| input.ranges.iter().any(|r| r.contains(i)))
+            .count())
+    }
+
+    fn render((ranges, ids): &(Vec<(u64, u64)>, Vec<u64>)) -> String {
+        let mut out = String::new();
+        for &(beg, len) in ranges {
+            out += &format!("{}-{}\n", beg, beg + len);
+        }
+        out.push('\n');
+        // Duplicate IDs are rejected by the parser.
+        let mut seen = std::collections::HashSet::new();
+        for id in ids.iter().filter(|&&id| seen.insert(id)) {
+            out += &format!("{}\n", id);
+        }
+        out
+    }
+
+    #[test]
+    fn test_ranges_op() {
+        let path = env::temp_dir().join(format!("day05-ranges-{}.txt", std::process::id()));
+        std::fs::write(&path, "3-5\n10-14\n").unwrap();
+        let path = path.to_str().unwrap();
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let union = ranges_op(path, "union", &args(&[path, "--pretty"])).unwrap();
+        assert_eq!("[3, 5]\n[10, 14]\n", union);
+        assert_eq!(
+            "6-9\n",
+            ranges_op(path, "complement", &args(&["4-12"])).unwrap()
+        );
+        let err = ranges_op(path, "xor", &args(&[path])).unwrap_err();
+        assert_eq!("unexpected operation: xor", err.to_string());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_query() {
+        let all = query(INPUT, &[]).expect("must query");
+        assert_eq!(
+            indoc! {"
+                1: spoiled, no range covers it
+                5: fresh, in line 1 (3-5)
+                8: spoiled, no range covers it
+                11: fresh, in line 2 (10-14)
+                17: fresh, in line 3 (16-20), line 4 (12-18)
+                32: spoiled, no range covers it
+                fresh: 3, spoiled: 3
+            "},
+            all
+        );
+        let one = query(INPUT, &["12".to_string()]).expect("must query");
+        assert_eq!(
+            "12: fresh, in line 2 (10-14), line 4 (12-18)\nfresh: 1, spoiled: 0\n",
+            one
+        );
+        assert!(query(INPUT, &["x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_part1_matches_scan() {
+        Harness::new().check(
+            "part1",
+            |rng| {
+                let ranges = (0..rng.range(1, 20))
+                    .map(|_| (rng.range(0, 100), rng.range(0, 10)))
+                    .collect();
+                let ids = (0..rng.below(20)).map(|_| rng.range(0, 120)).collect();
+                (ranges, ids)
+            },
+            |input| scan(&render(input)).expect("must solve"),
+            |input| part1(&render(input)).expect("must solve"),
+        );
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --release --nocapture"]
+    fn bench_part1() {
+        let mut rng = Rng::new(5);
+        let mut generate = |n: usize| {
+            let ranges = (0..n)
+                .map(|_| (rng.range(0, 1 << 40), rng.range(0, 1 << 20)))
+                .collect();
+            let ids = (0..n).map(|_| rng.range(0, 1 << 40)).collect();
+            render(&(ranges, ids))
+        };
+        let timed = |f: fn(&str) -> Result<usize>, input: &str| {
+            let now = std::time::Instant::now();
+            (f(input).expect("must solve"), now.elapsed())
+        };
+        for n in [1_000, 10_000] {
+            let input = generate(n);
+            let ((expected, old), (actual, new)) = (timed(scan, &input), timed(part1, &input));
+            assert_eq!(expected, actual);
+            println!("n={:>9} scan={:>12?} compact+search={:>12?}", n, old, new);
+        }
+        let input = generate(1_000_000);
+        let (fresh, elapsed) = timed(part1, &input);
+        println!(
+            "n={:>9} compact+search={:>12?} fresh={}",
+            1_000_000, elapsed, fresh
+        );
+    }
 }
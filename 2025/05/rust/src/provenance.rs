@@ -0,0 +1,143 @@
+use crate::Range;
+
+// The raw input ranges sorted by start, over a segment tree of their largest
+// end, so a lookup only visits subtrees that can still reach the ID.
+pub struct Index {
+    // range and the input line it came from
+    ranges: Vec<(Range, usize)>,
+    size: usize,
+    max_end: Vec<usize>,
+}
+
+impl Index {
+    // `lines[i]` is the input line of `ranges[i]`.
+    pub fn new(ranges: &[Range], lines: &[usize]) -> Self {
+        let mut ranges: Vec<(Range, usize)> =
+            ranges.iter().cloned().zip(lines.iter().copied()).collect();
+        ranges.sort_by_key(|(r, line)| (r.beg, *line));
+        let size = ranges.len().next_power_of_two();
+        let mut max_end = vec![0; 2 * size];
+        for (i, (r, _)) in ranges.iter().enumerate() {
+            max_end[size + i] = r.end;
+        }
+        for node in (1..size).rev() {
+            max_end[node] = max_end[2 * node].max(max_end[2 * node + 1]);
+        }
+        Index {
+            ranges,
+            size,
+            max_end,
+        }
+    }
+
+    // Every range containing `id` with its input line, by line.
+    pub fn matches(&self, id: usize) -> Vec<&(Range, usize)> {
+        let starts_before = self.ranges.partition_point(|(r, _)| r.beg <= id);
+        let mut out = Vec::new();
+        self.visit(1, 0, self.size, starts_before, id, &mut out);
+        let mut out: Vec<&(Range, usize)> = out.into_iter().map(|i| &self.ranges[i]).collect();
+        out.sort_by_key(|(_, line)| *line);
+        out
+    }
+
+    #[cfg(test)]
+    pub fn lines(&self, id: usize) -> Vec<usize> {
+        self.matches(id).iter().map(|(_, line)| *line).collect()
+    }
+
+    // Collects the ranges in leaves lo..hi below `node`, among the first
+    // `limit`, whose end reaches `id`.
+    fn visit(
+        &self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        limit: usize,
+        id: usize,
+        out: &mut Vec<usize>,
+    ) {
+        if lo >= limit || self.ranges.is_empty() || self.max_end[node] < id {
+            return;
+        }
+        if hi - lo == 1 {
+            out.push(lo);
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.visit(2 * node, lo, mid, limit, id, out);
+        self.visit(2 * node + 1, mid, hi, limit, id, out);
+    }
+
+    pub fn explain(&self, id: usize) -> String {
+        let matches = self.matches(id);
+        if matches.is_empty() {
+            return format!("{}: spoiled, no range covers it", id);
+        }
+        let sources: Vec<String> = matches
+            .iter()
+            .map(|(r, line)| format!("line {} ({}-{})", line, r.beg, r.end))
+            .collect();
+        format!("{}: fresh, in {}", id, sources.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use difftest::Harness;
+
+    fn index(ranges: &[(usize, usize)]) -> Index {
+        let ranges: Vec<Range> = ranges
+            .iter()
+            .map(|&(beg, end)| Range { beg, end })
+            .collect();
+        let lines: Vec<usize> = (1..=ranges.len()).collect();
+        Index::new(&ranges, &lines)
+    }
+
+    #[test]
+    fn test_provenance() {
+        let index = index(&[(3, 5), (10, 14), (16, 20), (12, 18)]);
+        assert_eq!(vec![3, 4], index.lines(17));
+        assert_eq!(vec![2, 4], index.lines(12));
+        assert_eq!(vec![1], index.lines(5));
+        assert!(index.lines(1).is_empty());
+        assert_eq!(
+            "17: fresh, in line 3 (16-20), line 4 (12-18)",
+            index.explain(17)
+        );
+        assert_eq!("32: spoiled, no range covers it", index.explain(32));
+        assert!(super::tests::index(&[]).lines(0).is_empty());
+        assert_eq!(vec![1, 2], super::tests::index(&[(4, 4), (0, 9)]).lines(4));
+    }
+
+    #[test]
+    fn test_lines_match_scan() {
+        Harness::new().check(
+            "provenance",
+            |rng| {
+                let ranges: Vec<(u64, u64)> = (0..rng.below(30))
+                    .map(|_| (rng.range(0, 60), rng.range(0, 15)))
+                    .collect();
+                (ranges, rng.range(0, 80))
+            },
+            |(ranges, id)| {
+                let id = *id as usize;
+                let lines = ranges.iter().enumerate();
+                lines
+                    .filter(|(_, (beg, len))| {
+                        (*beg as usize..=(*beg + *len) as usize).contains(&id)
+                    })
+                    .map(|(i, _)| i + 1)
+                    .collect::<Vec<_>>()
+            },
+            |(ranges, id)| {
+                let ranges: Vec<(usize, usize)> = ranges
+                    .iter()
+                    .map(|&(beg, len)| (beg as usize, (beg + len) as usize))
+                    .collect();
+                index(&ranges).lines(*id as usize)
+            },
+        );
+    }
+}
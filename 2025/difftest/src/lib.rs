@@ -0,0 +1,486 @@
+//! Differential testing harness shared by the daily solutions.
+//!
+//! A day registers a reference implementation (usually the straightforward
+//! one the puzzle was first solved with) next to an optimized one. The
+//! harness runs both on seeded random inputs and, on the first mismatch,
+//! shrinks the input down to a minimal failing case.
+//!
+//! The number of cases and the seed can be overridden with the
+//! `DIFFTEST_CASES` and `DIFFTEST_SEED` environment variables, which is how
+//! a failure printed by CI is reproduced locally.
+use std::fmt::{Debug, Display};
+
+const DEFAULT_CASES: usize = 2000;
+const DEFAULT_SEED: u64 = 0x5eed_2025;
+const MAX_SHRINK_STEPS: usize = 10_000;
+
+/// Small deterministic PRNG (splitmix64), good enough for input generation.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `lo..=hi`.
+    pub fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo <= hi, "empty range {}..={}", lo, hi);
+        match (hi - lo).checked_add(1) {
+            Some(span) => lo + self.next_u64() % span,
+            None => self.next_u64(),
+        }
+    }
+
+    /// Uniform value in `lo..=hi` for signed bounds.
+    pub fn range_i64(&mut self, lo: i64, hi: i64) -> i64 {
+        assert!(lo <= hi, "empty range {}..={}", lo, hi);
+        let span = hi.abs_diff(lo);
+        lo.wrapping_add_unsigned(self.range(0, span))
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "below(0)");
+        self.range(0, n as u64 - 1) as usize
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Produces "smaller" candidates of a value; used to minimise failing inputs.
+///
+/// Candidates should be strictly simpler than `self` so that shrinking
+/// terminates, and are tried in order, so put the most aggressive first.
+pub trait Shrink: Sized {
+    fn shrink(&self) -> Vec<Self>;
+}
+
+macro_rules! shrink_unsigned {
+    ($($t:ty),*) => {$(
+        impl Shrink for $t {
+            fn shrink(&self) -> Vec<Self> {
+                let x = *self;
+                let mut out = Vec::new();
+                if x == 0 {
+                    return out;
+                }
+                out.push(0);
+                if x / 2 != 0 {
+                    out.push(x / 2);
+                }
+                if x - 1 != 0 && x - 1 != x / 2 {
+                    out.push(x - 1);
+                }
+                out
+            }
+        }
+    )*};
+}
+
+macro_rules! shrink_signed {
+    ($($t:ty),*) => {$(
+        impl Shrink for $t {
+            fn shrink(&self) -> Vec<Self> {
+                let x = *self;
+                let mut out = Vec::new();
+                if x == 0 {
+                    return out;
+                }
+                out.push(0);
+                if x < 0 && x != <$t>::MIN {
+                    out.push(-x);
+                }
+                if x / 2 != 0 {
+                    out.push(x / 2);
+                }
+                let step = x - x.signum();
+                if step != 0 && step != x / 2 {
+                    out.push(step);
+                }
+                out
+            }
+        }
+    )*};
+}
+
+shrink_unsigned!(u8, u16, u32, u64, u128, usize);
+shrink_signed!(i8, i16, i32, i64, i128, isize);
+
+impl Shrink for bool {
+    fn shrink(&self) -> Vec<Self> {
+        if *self { vec![false] } else { vec![] }
+    }
+}
+
+impl Shrink for char {
+    fn shrink(&self) -> Vec<Self> {
+        vec![]
+    }
+}
+
+impl Shrink for String {
+    // Only ever removes characters: callers generate strings over a
+    // restricted alphabet (digits, grid cells) that must stay valid.
+    fn shrink(&self) -> Vec<Self> {
+        let chars: Vec<char> = self.chars().collect();
+        shrink_by_removal(&chars)
+            .into_iter()
+            .map(|v| v.into_iter().collect())
+            .collect()
+    }
+}
+
+impl<T: Shrink + Clone> Shrink for Vec<T> {
+    fn shrink(&self) -> Vec<Self> {
+        let mut out = shrink_by_removal(self);
+        for (i, item) in self.iter().enumerate() {
+            for smaller in item.shrink() {
+                let mut v = self.clone();
+                v[i] = smaller;
+                out.push(v);
+            }
+        }
+        out
+    }
+}
+
+fn shrink_by_removal<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    let mut out = Vec::new();
+    let n = items.len();
+    if n == 0 {
+        return out;
+    }
+    // drop whole chunks first (halves, quarters, ...) then single items
+    let mut chunk = n / 2;
+    while chunk > 1 {
+        for start in (0..n).step_by(chunk) {
+            let mut v = items[..start].to_vec();
+            v.extend_from_slice(&items[(start + chunk).min(n)..]);
+            out.push(v);
+        }
+        chunk /= 2;
+    }
+    for i in 0..n {
+        let mut v = items.to_vec();
+        v.remove(i);
+        out.push(v);
+    }
+    out
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone> Shrink for (A, B) {
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b) = self;
+        let mut out: Vec<Self> = a.shrink().into_iter().map(|a| (a, b.clone())).collect();
+        out.extend(b.shrink().into_iter().map(|b| (a.clone(), b)));
+        out
+    }
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone, C: Shrink + Clone> Shrink for (A, B, C) {
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b, c) = self;
+        let mut out: Vec<Self> = a
+            .shrink()
+            .into_iter()
+            .map(|a| (a, b.clone(), c.clone()))
+            .collect();
+        out.extend(b.shrink().into_iter().map(|b| (a.clone(), b, c.clone())));
+        out.extend(c.shrink().into_iter().map(|c| (a.clone(), b.clone(), c)));
+        out
+    }
+}
+
+/// A failing case, before and after shrinking.
+#[derive(Debug)]
+pub struct Mismatch<I, O> {
+    pub name: String,
+    pub case: usize,
+    pub seed: u64,
+    pub original: I,
+    pub input: I,
+    pub reference: O,
+    pub optimized: O,
+    pub shrink_steps: usize,
+}
+
+impl<I: Debug, O: Debug> Display for Mismatch<I, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}: mismatch on case {} (DIFFTEST_SEED={:#x})",
+            self.name, self.case, self.seed
+        )?;
+        writeln!(f, "  original:  {:?}", self.original)?;
+        writeln!(
+            f,
+            "  shrunk:    {:?} ({} steps)",
+            self.input, self.shrink_steps
+        )?;
+        writeln!(f, "  reference: {:?}", self.reference)?;
+        write!(f, "  optimized: {:?}", self.optimized)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Harness {
+    cases: usize,
+    seed: u64,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let cases = std::env::var("DIFFTEST_CASES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_CASES);
+        let seed = std::env::var("DIFFTEST_SEED")
+            .ok()
+            .and_then(|s| parse_seed(&s))
+            .unwrap_or(DEFAULT_SEED);
+        Self { cases, seed }
+    }
+
+    pub fn cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs `reference` and `optimized` on generated inputs and returns the
+    /// first mismatch, shrunk.
+    pub fn run<I, O, G, R, F>(
+        &self,
+        name: &str,
+        mut generate: G,
+        reference: R,
+        optimized: F,
+    ) -> Result<(), Box<Mismatch<I, O>>>
+    where
+        I: Shrink + Clone,
+        O: PartialEq,
+        G: FnMut(&mut Rng) -> I,
+        R: Fn(&I) -> O,
+        F: Fn(&I) -> O,
+    {
+        let mut rng = Rng::new(self.seed);
+        for case in 0..self.cases {
+            let input = generate(&mut rng);
+            let (expected, actual) = (reference(&input), optimized(&input));
+            if expected == actual {
+                continue;
+            }
+            let mut mismatch = Mismatch {
+                name: name.to_owned(),
+                case,
+                seed: self.seed,
+                original: input.clone(),
+                input,
+                reference: expected,
+                optimized: actual,
+                shrink_steps: 0,
+            };
+            shrink(&mut mismatch, &reference, &optimized);
+            return Err(Box::new(mismatch));
+        }
+        Ok(())
+    }
+
+    /// Same as [`Harness::run`], but panics with a readable report.
+    pub fn check<I, O, G, R, F>(&self, name: &str, generate: G, reference: R, optimized: F)
+    where
+        I: Shrink + Clone + Debug,
+        O: PartialEq + Debug,
+        G: FnMut(&mut Rng) -> I,
+        R: Fn(&I) -> O,
+        F: Fn(&I) -> O,
+    {
+        if let Err(mismatch) = self.run(name, generate, reference, optimized) {
+            panic!("{}", mismatch);
+        }
+    }
+}
+
+fn parse_seed(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn shrink<I, O, R, F>(m: &mut Mismatch<I, O>, reference: &R, optimized: &F)
+where
+    I: Shrink + Clone,
+    O: PartialEq,
+    R: Fn(&I) -> O,
+    F: Fn(&I) -> O,
+{
+    'outer: while m.shrink_steps < MAX_SHRINK_STEPS {
+        for candidate in m.input.shrink() {
+            let (expected, actual) = (reference(&candidate), optimized(&candidate));
+            if expected != actual {
+                m.input = candidate;
+                m.reference = expected;
+                m.optimized = actual;
+                m.shrink_steps += 1;
+                continue 'outer;
+            }
+        }
+        break;
+    }
+}
+
+/// Named reference/optimized pairs for one day, run together.
+///
+/// Every pair gets the same harness settings; all failures are collected
+/// before panicking so one broken part does not hide another.
+pub struct Suite {
+    harness: Harness,
+    checks: Vec<(String, Check)>,
+}
+
+type Check = Box<dyn Fn(&Harness) -> Option<String>>;
+
+impl Suite {
+    pub fn new(harness: Harness) -> Self {
+        Self {
+            harness,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn register<I, O, G, R, F>(
+        mut self,
+        name: &str,
+        generate: G,
+        reference: R,
+        optimized: F,
+    ) -> Self
+    where
+        I: Shrink + Clone + Debug + 'static,
+        O: PartialEq + Debug + 'static,
+        G: Fn(&mut Rng) -> I + 'static,
+        R: Fn(&I) -> O + 'static,
+        F: Fn(&I) -> O + 'static,
+    {
+        let owned = name.to_owned();
+        self.checks.push((
+            name.to_owned(),
+            Box::new(move |harness: &Harness| {
+                harness
+                    .run(&owned, &generate, &reference, &optimized)
+                    .err()
+                    .map(|m| m.to_string())
+            }),
+        ));
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.checks.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn run(&self) {
+        let failures: Vec<String> = self
+            .checks
+            .iter()
+            .filter_map(|(_, check)| check(&self.harness))
+            .collect();
+        if !failures.is_empty() {
+            panic!("{}", failures.join("\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let a: Vec<u64> = (0..8)
+            .scan(Rng::new(7), |r, _| Some(r.next_u64()))
+            .collect();
+        let b: Vec<u64> = (0..8)
+            .scan(Rng::new(7), |r, _| Some(r.next_u64()))
+            .collect();
+        assert_eq!(a, b);
+        let mut rng = Rng::new(1);
+        assert!((0..1000).all(|_| (3..=9).contains(&rng.range(3, 9))));
+        assert!((0..1000).all(|_| (-5..=5).contains(&rng.range_i64(-5, 5))));
+    }
+
+    #[test]
+    fn test_equal_implementations_pass() {
+        Harness::new().cases(500).check(
+            "sum",
+            |rng| {
+                (0..rng.below(10))
+                    .map(|_| rng.range(0, 100))
+                    .collect::<Vec<_>>()
+            },
+            |v: &Vec<u64>| v.iter().sum::<u64>(),
+            |v: &Vec<u64>| v.iter().rev().sum::<u64>(),
+        );
+    }
+
+    #[test]
+    fn test_mismatch_is_shrunk() {
+        // "optimized" forgets elements above 50; the minimal witness is [51]
+        let mismatch = Harness::new()
+            .cases(500)
+            .run(
+                "sum",
+                |rng| {
+                    (0..rng.below(20))
+                        .map(|_| rng.range(0, 100))
+                        .collect::<Vec<_>>()
+                },
+                |v: &Vec<u64>| v.iter().sum::<u64>(),
+                |v: &Vec<u64>| v.iter().filter(|&&x| x <= 50).sum::<u64>(),
+            )
+            .expect_err("must find mismatch");
+        assert_eq!(vec![51], mismatch.input);
+        assert_eq!((51, 0), (mismatch.reference, mismatch.optimized));
+    }
+
+    #[test]
+    fn test_suite_reports_every_failure() {
+        let suite = Suite::new(Harness::new().cases(100))
+            .register("ok", |rng| rng.range(0, 10), |x| *x, |x| *x)
+            .register("bad1", |rng| rng.range(0, 10), |x| *x, |x| x + 1)
+            .register("bad2", |rng| rng.range(0, 10), |x| *x, |x| x * 2 + 1);
+        assert_eq!(
+            vec!["ok", "bad1", "bad2"],
+            suite.names().collect::<Vec<_>>()
+        );
+        let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| suite.run()))
+            .expect_err("must panic");
+        let msg = err.downcast_ref::<String>().expect("string payload");
+        assert!(msg.contains("bad1") && msg.contains("bad2") && !msg.contains("ok:"));
+    }
+}